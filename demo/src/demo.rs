@@ -94,17 +94,26 @@ impl eframe::App for DemoApp {
                         )
                         .changed()
                     {
-                        let result = self.equation_factory.parse(&self.equation_str);
-                        if result.is_ok() {
-                            self.equation = result.unwrap();
-                            self.error = None;
-                        } else {
-                            self.error = Some(result.err().unwrap().message);
+                        // brackets still being typed are closed, so the graph does not flicker on every keystroke
+                        let report = self.equation_factory.parse_lenient(&self.equation_str);
+                        match report.equation {
+                            Some(equation) => {
+                                self.equation = equation;
+                                self.error = None;
+                            }
+                            None => {
+                                self.error =
+                                    report.errors.into_iter().next().map(|error| error.message)
+                            }
                         }
                     }
 
                     ui.radio_value(&mut self.syntax, Syntax::Standard, "Standard");
                     ui.radio_value(&mut self.syntax, Syntax::LaTeX, "LaTeX");
+                    ui.radio_value(&mut self.syntax, Syntax::AsciiMath, "AsciiMath");
+                    ui.radio_value(&mut self.syntax, Syntax::Spreadsheet, "Spreadsheet");
+                    ui.radio_value(&mut self.syntax, Syntax::Rpn, "RPN");
+                    ui.radio_value(&mut self.syntax, Syntax::SExpr, "S-expression");
 
                     if self.error.is_some() {
                         ui.label(
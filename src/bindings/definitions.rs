@@ -15,18 +15,71 @@ pub fn divide<T: NumericType>(args: &[T]) -> Value<T> {
 pub fn exponent<T: NumericType>(args: &[T]) -> Value<T> {
     Ok(args[0].powf(args[1]))
 }
+pub fn exp<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(args[0].exp())
+}
 pub fn pi<T: NumericType>(_: &[T]) -> Value<T> {
-    Ok(T::from(3.141592653589793238462643383280).unwrap())
+    Ok(T::from(std::f64::consts::PI).unwrap())
 }
 pub fn euler<T: NumericType>(_: &[T]) -> Value<T> {
-    Ok(T::from(2.718281828459045235360287471352).unwrap())
+    Ok(T::from(std::f64::consts::E).unwrap())
 }
 pub fn negate<T: NumericType>(args: &[T]) -> Value<T> {
     Ok(-args[0])
 }
+pub fn identity<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(args[0])
+}
+/// Logical not, where zero is false and any other value is true
+pub fn not<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(args[0].is_zero()))
+}
+
+// Comparisons and logical operators return 1 for true and 0 for false.
+// Any non-zero argument is treated as true, including NaN.
+fn boolean<T: NumericType>(value: bool) -> T {
+    if value {
+        T::one()
+    } else {
+        T::zero()
+    }
+}
+pub fn truth<T: NumericType>(_: &[T]) -> Value<T> {
+    Ok(T::one())
+}
+pub fn falsehood<T: NumericType>(_: &[T]) -> Value<T> {
+    Ok(T::zero())
+}
+pub fn equal<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(args[0] == args[1]))
+}
+pub fn not_equal<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(args[0] != args[1]))
+}
+pub fn less_than<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(args[0] < args[1]))
+}
+pub fn less_than_or_equal<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(args[0] <= args[1]))
+}
+pub fn greater_than<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(args[0] > args[1]))
+}
+pub fn greater_than_or_equal<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(args[0] >= args[1]))
+}
+pub fn and<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(!args[0].is_zero() && !args[1].is_zero()))
+}
+pub fn or<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(boolean(!args[0].is_zero() || !args[1].is_zero()))
+}
 pub fn sqrt<T: NumericType>(args: &[T]) -> Value<T> {
     Ok(args[0].sqrt())
 }
+pub fn root<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(args[1].powf(args[0].recip()))
+}
 pub fn sine<T: NumericType>(args: &[T]) -> Value<T> {
     Ok(args[0].sin())
 }
@@ -78,6 +131,9 @@ pub fn log<T: NumericType>(args: &[T]) -> Value<T> {
 pub fn log10<T: NumericType>(args: &[T]) -> Value<T> {
     Ok(args[0].log10())
 }
+pub fn logarithm<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(args[1].log(args[0]))
+}
 pub fn abs<T: NumericType>(args: &[T]) -> Value<T> {
     Ok(args[0].abs())
 }
@@ -94,8 +150,117 @@ pub fn round<T: NumericType>(args: &[T]) -> Value<T> {
     Ok(args[0].round())
 }
 pub fn min<T: NumericType>(args: &[T]) -> Value<T> {
-    Ok(args[0].min(args[1]))
+    Ok(args.iter().fold(T::infinity(), |a, &b| a.min(b)))
 }
 pub fn max<T: NumericType>(args: &[T]) -> Value<T> {
-    Ok(args[0].max(args[1]))
+    Ok(args.iter().fold(T::neg_infinity(), |a, &b| a.max(b)))
+}
+pub fn sum<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(args.iter().fold(T::zero(), |a, &b| a + b))
+}
+pub fn product<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(args.iter().fold(T::one(), |a, &b| a * b))
+}
+pub fn mean<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(sum(args)? / T::from(args.len()).unwrap())
+}
+pub fn percent<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(args[0] / T::from(100.0).unwrap())
+}
+pub fn factorial<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(gamma(args[0] + T::one()))
+}
+
+/// Gamma function using the Lanczos approximation, exact for small positive integers.
+/// Zero and the negative integers are poles, where the result is NaN
+fn gamma<T: NumericType>(x: T) -> T {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    let x = x.to_f64().unwrap_or(f64::NAN);
+    if x == x.floor() && x <= 0.0 {
+        return T::nan();
+    }
+    if x == x.floor() && (1.0..=171.0).contains(&x) {
+        let product = (2..x as u32).fold(1.0, |acc, n| acc * n as f64);
+        return T::from(product).unwrap_or(T::infinity());
+    }
+    if x < 0.5 {
+        // reflection formula
+        let pi = std::f64::consts::PI;
+        return T::from(pi / ((pi * x).sin() * gamma(T::from(1.0 - x).unwrap()).to_f64().unwrap()))
+            .unwrap_or(T::nan());
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    let result = (2.0 * std::f64::consts::PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * sum;
+    T::from(result).unwrap_or(T::infinity())
+}
+
+/// Sign of a number, where zero has sign 0
+pub fn sign<T: NumericType>(args: &[T]) -> Value<T> {
+    Ok(if args[0].is_zero() {
+        T::zero()
+    } else {
+        args[0].signum()
+    })
+}
+
+/// Polygamma function of integer order n, the (n+1)th derivative of the logarithm of the gamma function
+pub fn polygamma<T: NumericType>(args: &[T]) -> Value<T> {
+    // Bernoulli numbers B2, B4, ... for the asymptotic expansion
+    const BERNOULLI: [f64; 6] = [
+        1.0 / 6.0,
+        -1.0 / 30.0,
+        1.0 / 42.0,
+        -1.0 / 30.0,
+        5.0 / 66.0,
+        -691.0 / 2730.0,
+    ];
+
+    let order = args[0].to_f64().unwrap_or(f64::NAN);
+    let mut x = args[1].to_f64().unwrap_or(f64::NAN);
+    if order < 0.0 || order != order.floor() || !x.is_finite() {
+        return Ok(T::nan());
+    }
+    let n = order as i32;
+    let factorial = |k: i32| (1..=k).fold(1.0, |acc, i| acc * i as f64);
+    // (-1)^(n+1)
+    let sign = if n % 2 == 0 { -1.0 } else { 1.0 };
+
+    // shift x upwards using the recurrence relation until the asymptotic expansion is accurate
+    let mut result = 0.0;
+    while x < 10.0 + order {
+        result += sign * factorial(n) / x.powi(n + 1);
+        x += 1.0;
+    }
+    if n == 0 {
+        result += x.ln() - 0.5 / x;
+        for (i, b) in BERNOULLI.iter().enumerate() {
+            let k = 2 * (i as i32 + 1);
+            result -= b / (k as f64 * x.powi(k));
+        }
+    } else {
+        let mut series = factorial(n - 1) / x.powi(n) + factorial(n) / (2.0 * x.powi(n + 1));
+        for (i, b) in BERNOULLI.iter().enumerate() {
+            let k = 2 * (i as i32 + 1);
+            series += b * factorial(k + n - 1) / (factorial(k) * x.powi(k + n));
+        }
+        result += sign * series;
+    }
+    Ok(T::from(result).unwrap_or(T::nan()))
 }
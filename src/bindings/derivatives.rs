@@ -0,0 +1,224 @@
+//! Partial derivatives of the built-in bindings with respect to each of their arguments
+
+use crate::{calculus::Term, error::Error, NumericType};
+
+type Partials<T> = Result<Vec<Term<T>>, Error>;
+
+fn call<T: NumericType<ExprType = T>>(label: &str, args: &[&Term<T>]) -> Term<T> {
+    Term::call(label, args.iter().map(|&arg| arg.clone()).collect())
+}
+fn number<T: NumericType<ExprType = T>>(value: f64) -> Term<T> {
+    Term::number(value)
+}
+
+pub fn constant<T: NumericType<ExprType = T>>(_: &[Term<T>]) -> Partials<T> {
+    Ok(vec![])
+}
+pub fn add<T: NumericType<ExprType = T>>(_: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(1.0), number(1.0)])
+}
+pub fn subtract<T: NumericType<ExprType = T>>(_: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(1.0), number(-1.0)])
+}
+pub fn multiply<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![args[1].clone(), args[0].clone()])
+}
+pub fn divide<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let (a, b) = (&args[0], &args[1]);
+    Ok(vec![
+        number(1.0) / b.clone(),
+        -(a.clone() / b.clone().pow(number(2.0))),
+    ])
+}
+pub fn exponent<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let (a, b) = (&args[0], &args[1]);
+    Ok(vec![
+        b.clone() * a.clone().pow(b.clone() - number(1.0)),
+        a.clone().pow(b.clone()) * call("LogBaseE", &[a]),
+    ])
+}
+/// a % b = a - b * trunc(a / b)
+pub fn modulo<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let quotient = args[0].clone() / args[1].clone();
+    let truncated = call("Sign", &[&quotient]) * call("Floor", &[&call("Absolute", &[&quotient])]);
+    Ok(vec![number(1.0), -truncated])
+}
+/// Each argument contributes only where it is the smallest, split evenly between ties
+pub fn min<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(extreme_partials(args, -1.0))
+}
+/// Each argument contributes only where it is the greatest, split evenly between ties
+pub fn max<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(extreme_partials(args, 1.0))
+}
+fn extreme_partials<T: NumericType<ExprType = T>>(
+    args: &[Term<T>],
+    direction: f64,
+) -> Vec<Term<T>> {
+    // 1 for each argument at least as extreme as every other, using (2 + sign(d) - |sign(d)|) / 2 as d >= 0
+    let at_extreme: Vec<Term<T>> = (0..args.len())
+        .map(|i| {
+            (0..args.len())
+                .filter(|&j| j != i)
+                .fold(number(1.0), |acc, j| {
+                    let difference = number(direction) * (args[i].clone() - args[j].clone());
+                    let sign = call("Sign", &[&difference]);
+                    acc * ((number(2.0) + sign.clone() - call("Absolute", &[&sign])) / number(2.0))
+                })
+        })
+        .collect();
+    let ties = at_extreme
+        .iter()
+        .fold(number(0.0), |acc, term| acc + term.clone());
+    at_extreme
+        .into_iter()
+        .map(|term| term / ties.clone())
+        .collect()
+}
+pub fn sum<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(1.0); args.len()])
+}
+/// Each partial derivative is the product of the other arguments
+pub fn product<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok((0..args.len())
+        .map(|i| {
+            (0..args.len())
+                .filter(|&j| j != i)
+                .fold(number(1.0), |acc, j| acc * args[j].clone())
+        })
+        .collect())
+}
+pub fn mean<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(1.0 / args.len() as f64); args.len()])
+}
+pub fn negate<T: NumericType<ExprType = T>>(_: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(-1.0)])
+}
+pub fn identity<T: NumericType<ExprType = T>>(_: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(1.0)])
+}
+/// Derivative of a piecewise constant function, which is zero everywhere it is defined
+pub fn piecewise_constant<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(0.0); args.len()])
+}
+/// d/dx Γ(x + 1) = Γ(x + 1)ψ(x + 1)
+pub fn factorial<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        call("Factorial", &[&args[0]])
+            * call(
+                "Polygamma",
+                &[&number(0.0), &(args[0].clone() + number(1.0))],
+            ),
+    ])
+}
+/// The order of a polygamma function is an integer, so has no derivative
+pub fn polygamma<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        number(f64::NAN),
+        call("Polygamma", &[&(args[0].clone() + number(1.0)), &args[1]]),
+    ])
+}
+pub fn percent<T: NumericType<ExprType = T>>(_: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(0.01)])
+}
+pub fn sqrt<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        number(1.0) / (number(2.0) * call("SquareRoot", &[&args[0]])),
+    ])
+}
+/// root(n, x) = x^(1/n)
+pub fn root<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let (n, x) = (&args[0], &args[1]);
+    let root = call("Root", &[n, x]);
+    Ok(vec![
+        -(root.clone() * call("LogBaseE", &[x]) / n.clone().pow(number(2.0))),
+        root / (n.clone() * x.clone()),
+    ])
+}
+pub fn sine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![call("Cosine", &[&args[0]])])
+}
+pub fn cosine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![-call("Sine", &[&args[0]])])
+}
+pub fn tangent<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![call("Secant", &[&args[0]]).pow(number(2.0))])
+}
+pub fn inverse_sine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let x = &args[0];
+    Ok(vec![
+        number(1.0) / call("SquareRoot", &[&(number(1.0) - x.clone().pow(number(2.0)))]),
+    ])
+}
+pub fn inverse_cosine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![-inverse_sine(args)?.remove(0)])
+}
+pub fn inverse_tangent<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        number(1.0) / (number(1.0) + args[0].clone().pow(number(2.0))),
+    ])
+}
+pub fn hyperbolic_sine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![call("HypCosine", &[&args[0]])])
+}
+pub fn hyperbolic_cosine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![call("HypSine", &[&args[0]])])
+}
+pub fn hyperbolic_tangent<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        number(1.0) - call("HypTangent", &[&args[0]]).pow(number(2.0)),
+    ])
+}
+pub fn inverse_hyperbolic_sine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let x = &args[0];
+    Ok(vec![
+        number(1.0) / call("SquareRoot", &[&(x.clone().pow(number(2.0)) + number(1.0))]),
+    ])
+}
+pub fn inverse_hyperbolic_cosine<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let x = &args[0];
+    Ok(vec![
+        number(1.0) / call("SquareRoot", &[&(x.clone().pow(number(2.0)) - number(1.0))]),
+    ])
+}
+pub fn inverse_hyperbolic_tangent<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        number(1.0) / (number(1.0) - args[0].clone().pow(number(2.0))),
+    ])
+}
+pub fn cosecant<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        -(call("Cosecant", &[&args[0]]) * call("Cotangent", &[&args[0]])),
+    ])
+}
+pub fn secant<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        call("Secant", &[&args[0]]) * call("Tangent", &[&args[0]]),
+    ])
+}
+pub fn cotangent<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![-call("Cosecant", &[&args[0]]).pow(number(2.0))])
+}
+pub fn exp<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![call("Exp", &[&args[0]])])
+}
+pub fn log<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![number(1.0) / args[0].clone()])
+}
+pub fn log10<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![
+        number(1.0) / (args[0].clone() * number(std::f64::consts::LN_10)),
+    ])
+}
+/// log_b(x) = ln(x) / ln(b)
+pub fn logarithm<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    let (b, x) = (&args[0], &args[1]);
+    let ln_b = call("LogBaseE", &[b]);
+    Ok(vec![
+        -(call("LogBaseE", &[x]) / (b.clone() * ln_b.clone().pow(number(2.0)))),
+        number(1.0) / (x.clone() * ln_b),
+    ])
+}
+pub fn abs<T: NumericType<ExprType = T>>(args: &[Term<T>]) -> Partials<T> {
+    Ok(vec![call("Sign", &[&args[0]])])
+}
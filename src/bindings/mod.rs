@@ -1,11 +1,13 @@
 mod definitions;
+mod derivatives;
 
 use std::collections::HashMap;
 use std::sync::{LazyLock, RwLock};
 
+pub use crate::calculus::DerivativePointer;
 use crate::error::{return_error, Error, ErrorType};
 use crate::expressions::function::Function;
-pub use crate::expressions::function::FunctionPointer;
+pub use crate::expressions::function::{Arity, FunctionPointer};
 use crate::NumericType;
 
 type BindingMap<T> = HashMap<&'static str, Function<T>>;
@@ -16,18 +18,29 @@ pub trait FunctionBindings {
     fn get_bindings() -> BindingMap<Self::ExprType>;
 
     fn register_bindings(
-        bindings: &'static [(&str, FunctionPointer<Self::ExprType>, usize)],
+        bindings: &'static [(&str, FunctionPointer<Self::ExprType>, Arity)],
+    ) -> Result<(), Error>;
+
+    /// Adds derivative rules to bindings that have already been registered
+    fn register_derivatives(
+        derivatives: &'static [(&str, DerivativePointer<Self::ExprType>)],
     ) -> Result<(), Error>;
 
     fn get_binding(label: &str) -> Option<Function<Self::ExprType>>;
 }
 
 pub fn register_bindings<T: NumericType + FunctionBindings<ExprType = T>>(
-    bindings: &'static [(&'static str, FunctionPointer<T>, usize)],
+    bindings: &'static [(&'static str, FunctionPointer<T>, Arity)],
 ) -> Result<(), Error> {
     <T as FunctionBindings>::register_bindings(bindings)
 }
 
+pub fn register_derivatives<T: NumericType + FunctionBindings<ExprType = T>>(
+    derivatives: &'static [(&'static str, DerivativePointer<T>)],
+) -> Result<(), Error> {
+    <T as FunctionBindings>::register_derivatives(derivatives)
+}
+
 pub fn get_bindings<T: NumericType + FunctionBindings<ExprType = T>>() -> BindingMap<T> {
     <T as FunctionBindings>::get_bindings()
 }
@@ -35,41 +48,123 @@ pub fn get_bindings<T: NumericType + FunctionBindings<ExprType = T>>() -> Bindin
 macro_rules! register_supported_type {
     ( $($Type:r#ident),+ ) => { $( paste::paste! {
 
-        static [<$Type:upper _DEFINITIONS>]: &[(&'static str, FunctionPointer<$Type>, usize)] = &[
-
-            ("Pi", definitions::pi, 0),
-            ("Euler", definitions::euler, 0),
-            ("Add", definitions::add, 2),
-            ("Subtract", definitions::subtract, 2),
-            ("Multiply", definitions::multiply, 2),
-            ("Divide", definitions::divide, 2),
-            ("Exponent", definitions::exponent, 2),
-            ("Modulo", definitions::modulo, 2),
-            ("Min", definitions::min, 2),
-            ("Max", definitions::max, 2),
-            ("Negate", definitions::negate, 1),
-            ("SquareRoot", definitions::sqrt, 1),
-            ("Sine", definitions::sine, 1),
-            ("Cosine", definitions::cosine, 1),
-            ("Tangent", definitions::tangent, 1),
-            ("Arcsine", definitions::inverse_sine, 1),
-            ("Arccosine", definitions::inverse_cosine, 1),
-            ("Arctangent", definitions::inverse_tangent, 1),
-            ("HypSine", definitions::hyperbolic_sine, 1),
-            ("HypCosine", definitions::hyperbolic_cosine, 1),
-            ("HypTangent", definitions::hyperbolic_tangent, 1),
-            ("InvHypSine", definitions::inverse_hyperbolic_sine, 1),
-            ("InvHypCosine", definitions::inverse_hyperbolic_cosine, 1),
-            ("InvHypTangent", definitions::inverse_hyperbolic_tangent, 1),
-            ("Cosecant", definitions::cosecant, 1),
-            ("Secant", definitions::secant, 1),
-            ("Cotangent", definitions::cotangent, 1),
-            ("LogBaseE", definitions::log, 1),
-            ("LogBase10", definitions::log10, 1),
-            ("Absolute", definitions::abs, 1),
-            ("Round", definitions::round, 1),
-            ("Ceiling", definitions::ceiling, 1),
-            ("Floor", definitions::floor, 1),
+        static [<$Type:upper _DEFINITIONS>]: &[(&'static str, FunctionPointer<$Type>, Arity)] = &[
+
+            ("Pi", definitions::pi, Arity::Exactly(0)),
+            ("Euler", definitions::euler, Arity::Exactly(0)),
+            ("Add", definitions::add, Arity::Exactly(2)),
+            ("Subtract", definitions::subtract, Arity::Exactly(2)),
+            ("Multiply", definitions::multiply, Arity::Exactly(2)),
+            ("Divide", definitions::divide, Arity::Exactly(2)),
+            ("Exponent", definitions::exponent, Arity::Exactly(2)),
+            ("Exp", definitions::exp, Arity::Exactly(1)),
+            ("Modulo", definitions::modulo, Arity::Exactly(2)),
+            ("Min", definitions::min, Arity::AtLeast(1)),
+            ("Max", definitions::max, Arity::AtLeast(1)),
+            ("Sum", definitions::sum, Arity::AtLeast(0)),
+            ("Product", definitions::product, Arity::AtLeast(0)),
+            ("Mean", definitions::mean, Arity::AtLeast(1)),
+            ("Negate", definitions::negate, Arity::Exactly(1)),
+            ("Identity", definitions::identity, Arity::Exactly(1)),
+            ("Not", definitions::not, Arity::Exactly(1)),
+            ("Factorial", definitions::factorial, Arity::Exactly(1)),
+            ("Percent", definitions::percent, Arity::Exactly(1)),
+            ("SquareRoot", definitions::sqrt, Arity::Exactly(1)),
+            ("Root", definitions::root, Arity::Exactly(2)),
+            ("Sine", definitions::sine, Arity::Exactly(1)),
+            ("Cosine", definitions::cosine, Arity::Exactly(1)),
+            ("Tangent", definitions::tangent, Arity::Exactly(1)),
+            ("Arcsine", definitions::inverse_sine, Arity::Exactly(1)),
+            ("Arccosine", definitions::inverse_cosine, Arity::Exactly(1)),
+            ("Arctangent", definitions::inverse_tangent, Arity::Exactly(1)),
+            ("HypSine", definitions::hyperbolic_sine, Arity::Exactly(1)),
+            ("HypCosine", definitions::hyperbolic_cosine, Arity::Exactly(1)),
+            ("HypTangent", definitions::hyperbolic_tangent, Arity::Exactly(1)),
+            ("InvHypSine", definitions::inverse_hyperbolic_sine, Arity::Exactly(1)),
+            ("InvHypCosine", definitions::inverse_hyperbolic_cosine, Arity::Exactly(1)),
+            ("InvHypTangent", definitions::inverse_hyperbolic_tangent, Arity::Exactly(1)),
+            ("Cosecant", definitions::cosecant, Arity::Exactly(1)),
+            ("Secant", definitions::secant, Arity::Exactly(1)),
+            ("Cotangent", definitions::cotangent, Arity::Exactly(1)),
+            ("LogBaseE", definitions::log, Arity::Exactly(1)),
+            ("LogBase10", definitions::log10, Arity::Exactly(1)),
+            ("Logarithm", definitions::logarithm, Arity::Exactly(2)),
+            ("Absolute", definitions::abs, Arity::Exactly(1)),
+            ("Round", definitions::round, Arity::Exactly(1)),
+            ("Ceiling", definitions::ceiling, Arity::Exactly(1)),
+            ("Floor", definitions::floor, Arity::Exactly(1)),
+            ("Sign", definitions::sign, Arity::Exactly(1)),
+            ("Polygamma", definitions::polygamma, Arity::Exactly(2)),
+            ("True", definitions::truth, Arity::Exactly(0)),
+            ("False", definitions::falsehood, Arity::Exactly(0)),
+            ("Equal", definitions::equal, Arity::Exactly(2)),
+            ("NotEqual", definitions::not_equal, Arity::Exactly(2)),
+            ("LessThan", definitions::less_than, Arity::Exactly(2)),
+            ("LessThanOrEqual", definitions::less_than_or_equal, Arity::Exactly(2)),
+            ("GreaterThan", definitions::greater_than, Arity::Exactly(2)),
+            ("GreaterThanOrEqual", definitions::greater_than_or_equal, Arity::Exactly(2)),
+            ("And", definitions::and, Arity::Exactly(2)),
+            ("Or", definitions::or, Arity::Exactly(2)),
+
+        ];
+
+        static [<$Type:upper _DERIVATIVES>]: &[(&'static str, DerivativePointer<$Type>)] = &[
+
+            ("Pi", derivatives::constant),
+            ("Euler", derivatives::constant),
+            ("Add", derivatives::add),
+            ("Subtract", derivatives::subtract),
+            ("Multiply", derivatives::multiply),
+            ("Divide", derivatives::divide),
+            ("Exponent", derivatives::exponent),
+            ("Exp", derivatives::exp),
+            ("Modulo", derivatives::modulo),
+            ("Min", derivatives::min),
+            ("Max", derivatives::max),
+            ("Sum", derivatives::sum),
+            ("Product", derivatives::product),
+            ("Mean", derivatives::mean),
+            ("Negate", derivatives::negate),
+            ("Identity", derivatives::identity),
+            ("Not", derivatives::piecewise_constant),
+            ("Factorial", derivatives::factorial),
+            ("Percent", derivatives::percent),
+            ("SquareRoot", derivatives::sqrt),
+            ("Root", derivatives::root),
+            ("Sine", derivatives::sine),
+            ("Cosine", derivatives::cosine),
+            ("Tangent", derivatives::tangent),
+            ("Arcsine", derivatives::inverse_sine),
+            ("Arccosine", derivatives::inverse_cosine),
+            ("Arctangent", derivatives::inverse_tangent),
+            ("HypSine", derivatives::hyperbolic_sine),
+            ("HypCosine", derivatives::hyperbolic_cosine),
+            ("HypTangent", derivatives::hyperbolic_tangent),
+            ("InvHypSine", derivatives::inverse_hyperbolic_sine),
+            ("InvHypCosine", derivatives::inverse_hyperbolic_cosine),
+            ("InvHypTangent", derivatives::inverse_hyperbolic_tangent),
+            ("Cosecant", derivatives::cosecant),
+            ("Secant", derivatives::secant),
+            ("Cotangent", derivatives::cotangent),
+            ("LogBaseE", derivatives::log),
+            ("LogBase10", derivatives::log10),
+            ("Logarithm", derivatives::logarithm),
+            ("Absolute", derivatives::abs),
+            ("Round", derivatives::piecewise_constant),
+            ("Ceiling", derivatives::piecewise_constant),
+            ("Floor", derivatives::piecewise_constant),
+            ("Sign", derivatives::piecewise_constant),
+            ("Polygamma", derivatives::polygamma),
+            ("True", derivatives::constant),
+            ("False", derivatives::constant),
+            ("Equal", derivatives::piecewise_constant),
+            ("NotEqual", derivatives::piecewise_constant),
+            ("LessThan", derivatives::piecewise_constant),
+            ("LessThanOrEqual", derivatives::piecewise_constant),
+            ("GreaterThan", derivatives::piecewise_constant),
+            ("GreaterThanOrEqual", derivatives::piecewise_constant),
+            ("And", derivatives::piecewise_constant),
+            ("Or", derivatives::piecewise_constant),
 
         ];
 
@@ -84,7 +179,7 @@ macro_rules! register_supported_type {
                 let mut map: BindingMap<Self::ExprType> = HashMap::with_capacity(
                     [<$Type:upper _DEFINITIONS>].len()
                 );
-                for (label, func, arg_count) in [<$Type:upper _DEFINITIONS>] {
+                for (label, func, arity) in [<$Type:upper _DEFINITIONS>] {
                     match map.get(label) {
                         Some(_) => {
                             panic!(
@@ -92,17 +187,28 @@ macro_rules! register_supported_type {
                                 label, std::any::type_name::<$Type>()
                             );
                         },
-                        None => { map.insert(label, Function::new(label, func, *arg_count)); }
+                        None => { map.insert(label, Function::new(label, func, *arity)); }
+                    }
+                }
+                for (label, derivative) in [<$Type:upper _DERIVATIVES>] {
+                    match map.get_mut(label) {
+                        Some(binding) => binding.derivative = Some(derivative),
+                        None => {
+                            panic!(
+                                "Built-in derivative defined for missing binding '{}' and type {}",
+                                label, std::any::type_name::<$Type>()
+                            );
+                        }
                     }
                 }
                 map
             }
 
-            fn register_bindings(bindings: &'static[(&str, FunctionPointer<$Type>, usize)]) -> Result<(), Error> {
+            fn register_bindings(bindings: &'static[(&str, FunctionPointer<$Type>, Arity)]) -> Result<(), Error> {
                 let mut binding_map = [<$Type:upper _BINDINGS>].write().unwrap();
-                for (label, func, arg_count) in bindings {
+                for (label, func, arity) in bindings {
                     match binding_map.contains_key(label) {
-                        false => { binding_map.insert(label, Function::new(label, func, *arg_count)); },
+                        false => { binding_map.insert(label, Function::new(label, func, *arity)); },
                         true => {
                             return_error!(
                                 ErrorType::BindingError,
@@ -115,6 +221,23 @@ macro_rules! register_supported_type {
                 Ok(())
             }
 
+            fn register_derivatives(derivatives: &'static[(&str, DerivativePointer<$Type>)]) -> Result<(), Error> {
+                let mut binding_map = [<$Type:upper _BINDINGS>].write().unwrap();
+                for (label, derivative) in derivatives {
+                    match binding_map.get_mut(label) {
+                        Some(binding) => binding.derivative = Some(derivative),
+                        None => {
+                            return_error!(
+                                ErrorType::BindingError,
+                                "No binding registered for label '{}' and type {}",
+                                label, std::any::type_name::<$Type>()
+                            )
+                        }
+                    }
+                }
+                Ok(())
+            }
+
             fn get_binding(label: &str) -> Option<Function<Self::ExprType>> {
                 let bindings = [<$Type:upper _BINDINGS>].read().unwrap();
                 let binding = bindings.get(label)?;
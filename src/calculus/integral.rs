@@ -0,0 +1,146 @@
+use crate::{
+    equation::Value,
+    error::{return_error, Error, ErrorType},
+    NumericType,
+};
+
+// nodes and weights of the 15 point Kronrod rule, and the weights of the 7 point Gauss rule embedded in it
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_18,
+    0.140_653_259_715_525_92,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_83,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+/// Settings for numerical integration
+#[derive(Clone, Copy, Debug)]
+pub struct IntegrationOptions<T: NumericType> {
+    /// integration stops once the estimated error is below this, relative to the size of the result if it is greater than 1
+    pub tolerance: T,
+    /// maximum number of times the range may be split into smaller intervals
+    pub max_subdivisions: usize,
+}
+
+impl<T: NumericType> Default for IntegrationOptions<T> {
+    fn default() -> Self {
+        IntegrationOptions {
+            tolerance: T::epsilon().sqrt(),
+            max_subdivisions: 100,
+        }
+    }
+}
+
+/// The result of a numerical integration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Integral<T: NumericType> {
+    pub value: T,
+    /// estimated absolute error of the value, which may be above the tolerance if the maximum number of subdivisions was reached
+    pub error_estimate: T,
+}
+
+struct Interval<T> {
+    lower: T,
+    upper: T,
+    value: T,
+    error: T,
+}
+
+/// Integrates a function between two bounds using adaptive Gauss-Kronrod quadrature,
+/// repeatedly splitting the interval with the largest error estimate
+pub(crate) fn integrate<T: NumericType>(
+    mut function: impl FnMut(T) -> Value<T>,
+    lower: T,
+    upper: T,
+    options: &IntegrationOptions<T>,
+) -> Result<Integral<T>, Error> {
+    if !lower.is_finite() || !upper.is_finite() {
+        return_error!(
+            ErrorType::EvaluationError,
+            "Bounds of integral must be finite but were {} and {}",
+            lower,
+            upper
+        );
+    }
+
+    let mut intervals = vec![gauss_kronrod(&mut function, lower, upper)?];
+    for _ in 0..options.max_subdivisions {
+        let value = intervals.iter().fold(T::zero(), |acc, i| acc + i.value);
+        let error = intervals.iter().fold(T::zero(), |acc, i| acc + i.error);
+        if error <= options.tolerance * value.abs().max(T::one()) {
+            break;
+        }
+        let (index, _) = intervals.iter().enumerate().fold(
+            (0, T::neg_infinity()),
+            |worst, (index, interval)| match interval.error > worst.1 {
+                true => (index, interval.error),
+                false => worst,
+            },
+        );
+        let interval = intervals.swap_remove(index);
+        let middle = (interval.lower + interval.upper) / T::from(2.0).unwrap();
+        if middle == interval.lower || middle == interval.upper {
+            // the interval cannot be split any further
+            intervals.push(interval);
+            break;
+        }
+        intervals.push(gauss_kronrod(&mut function, interval.lower, middle)?);
+        intervals.push(gauss_kronrod(&mut function, middle, interval.upper)?);
+    }
+
+    Ok(Integral {
+        value: intervals.iter().fold(T::zero(), |acc, i| acc + i.value),
+        error_estimate: intervals.iter().fold(T::zero(), |acc, i| acc + i.error),
+    })
+}
+
+/// Estimates the integral over one interval with the 15 point Kronrod rule,
+/// using the difference from the 7 point Gauss rule as the error
+fn gauss_kronrod<T: NumericType>(
+    function: &mut impl FnMut(T) -> Value<T>,
+    lower: T,
+    upper: T,
+) -> Result<Interval<T>, Error> {
+    let constant = |value: f64| T::from(value).unwrap();
+    let half_length = (upper - lower) / constant(2.0);
+    let centre = lower + half_length;
+
+    let centre_value = function(centre)?;
+    let mut kronrod = centre_value * constant(KRONROD_WEIGHTS[7]);
+    let mut gauss = centre_value * constant(GAUSS_WEIGHTS[3]);
+    for i in 0..7 {
+        let offset = half_length * constant(KRONROD_NODES[i]);
+        let pair = function(centre - offset)? + function(centre + offset)?;
+        kronrod = kronrod + pair * constant(KRONROD_WEIGHTS[i]);
+        // odd nodes are shared with the Gauss rule
+        if i % 2 == 1 {
+            gauss = gauss + pair * constant(GAUSS_WEIGHTS[i / 2]);
+        }
+    }
+
+    Ok(Interval {
+        lower,
+        upper,
+        value: kronrod * half_length,
+        error: ((kronrod - gauss) * half_length).abs(),
+    })
+}
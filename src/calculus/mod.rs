@@ -0,0 +1,273 @@
+mod integral;
+
+pub(crate) use integral::integrate;
+pub use integral::{Integral, IntegrationOptions};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::rc::Rc;
+
+use crate::{
+    bindings::FunctionBindings,
+    error::{return_error, Error, ErrorCode, ErrorType},
+    expressions::{number::Number, variable::Variable, Expression},
+    NumericType,
+};
+
+/// Returns the partial derivatives of a function with respect to each of its arguments
+pub type DerivativePointer<T> = fn(&[Term<T>]) -> Result<Vec<Term<T>>, Error>;
+
+/// A symbolic expression tree, used to define and calculate derivatives
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term<T: NumericType> {
+    Number(T),
+    Variable(String),
+    /// a function binding label and its arguments
+    Function(String, Vec<Term<T>>),
+}
+
+impl<T: NumericType<ExprType = T>> Term<T> {
+    pub fn number(value: f64) -> Term<T> {
+        Term::Number(T::from(value).unwrap())
+    }
+
+    /// Applies the binding with the given label, evaluating it straight away if all arguments are numbers
+    pub fn call(label: &str, args: Vec<Term<T>>) -> Term<T> {
+        let values: Vec<T> = args
+            .iter()
+            .filter_map(|arg| match arg {
+                Term::Number(value) => Some(*value),
+                _ => None,
+            })
+            .collect();
+        if values.len() == args.len() {
+            if let Some(binding) = <T as FunctionBindings>::get_binding(label) {
+                if binding.arity.accepts(values.len()) {
+                    if let Ok(value) = (binding.function)(&values) {
+                        return Term::Number(value);
+                    }
+                }
+            }
+        }
+        Term::Function(label.to_string(), args)
+    }
+
+    pub fn pow(self, exponent: Term<T>) -> Term<T> {
+        if exponent.is(0.0) {
+            return Term::number(1.0);
+        }
+        if exponent.is(1.0) {
+            return self;
+        }
+        Term::call("Exponent", vec![self, exponent])
+    }
+
+    fn is(&self, value: f64) -> bool {
+        matches!(self, Term::Number(n) if *n == T::from(value).unwrap())
+    }
+
+    /// Calculates the derivative of this term with respect to a variable
+    pub fn derivative(&self, variable: &str) -> Result<Term<T>, Error> {
+        match self {
+            Term::Number(_) => Ok(Term::number(0.0)),
+            Term::Variable(label) => Ok(Term::number(if label == variable { 1.0 } else { 0.0 })),
+            Term::Function(label, args) => {
+                let rule = match <T as FunctionBindings>::get_binding(label) {
+                    Some(binding) => match binding.derivative {
+                        Some(rule) => rule,
+                        None => return_error!(
+                            ErrorType::DifferentiationError,
+                            "Binding '{}' has no derivative rule",
+                            label
+                        ),
+                    },
+                    None => return_error!(
+                        ErrorType::DifferentiationError,
+                        "No binding found with label '{}'",
+                        label
+                    ),
+                };
+                let partials = rule(args)?;
+                if partials.len() != args.len() {
+                    return_error!(
+                        ErrorType::DifferentiationError,
+                        "Derivative rule for '{}' returned {} partial derivatives for {} arguments",
+                        label,
+                        partials.len(),
+                        args.len()
+                    );
+                }
+                // chain rule: sum of each partial derivative multiplied by the derivative of its argument
+                let mut result = Term::number(0.0);
+                for (partial, arg) in partials.into_iter().zip(args) {
+                    let arg_derivative = arg.derivative(variable)?;
+                    if !arg_derivative.is(0.0) {
+                        result = result + partial * arg_derivative;
+                    }
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    /// Replaces any variable with a label in the map by the term it maps to
+    pub(crate) fn substitute(&self, values: &HashMap<String, Term<T>>) -> Term<T> {
+        match self {
+            Term::Number(_) => self.clone(),
+            Term::Variable(label) => values.get(label).cloned().unwrap_or_else(|| self.clone()),
+            Term::Function(label, args) => Term::call(
+                label,
+                args.iter().map(|arg| arg.substitute(values)).collect(),
+            ),
+        }
+    }
+
+    /// Converts the term into a list of expressions to be evaluated left to right
+    pub(crate) fn to_expressions(
+        &self,
+        expressions: &mut Vec<Box<dyn Expression<ExprType = T>>>,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+    ) -> Result<(), Error> {
+        match self {
+            Term::Number(value) => expressions.push(Box::new(Number::new(*value))),
+            Term::Variable(label) => {
+                let cell = variables
+                    .entry(label.clone())
+                    .or_insert_with(|| Rc::new(RefCell::new(T::zero())));
+                expressions.push(Box::new(Variable::new(label, cell)));
+            }
+            Term::Function(label, args) => {
+                for arg in args {
+                    arg.to_expressions(expressions, variables)?;
+                }
+                match <T as FunctionBindings>::get_binding(label) {
+                    Some(binding) if binding.arity.accepts(args.len()) => {
+                        expressions.push(Box::new(binding.with_inputs(args.len())))
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            ErrorType::BindingError,
+                            format!(
+                                "No binding found with label '{}' that takes {} arguments",
+                                label,
+                                args.len()
+                            ),
+                        )
+                        .with_code(ErrorCode::UnknownBinding))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts a list of expressions evaluated left to right into a term
+    pub(crate) fn from_expressions(
+        expressions: &[Box<dyn Expression<ExprType = T>>],
+    ) -> Result<Term<T>, Error> {
+        let mut stack = Vec::new();
+        for expression in expressions {
+            if expression.num_inputs() > stack.len() {
+                return_error!(
+                    ErrorType::SyntaxError,
+                    "Function {} requires {} inputs but the output stack contains {}",
+                    expression,
+                    expression.num_inputs(),
+                    stack.len()
+                );
+            }
+            let inputs = stack.split_off(stack.len() - expression.num_inputs());
+            stack.push(expression.to_term(inputs)?);
+        }
+        match stack.pop() {
+            Some(term) if stack.is_empty() => Ok(term),
+            _ => return_error!(
+                ErrorType::SyntaxError,
+                "Equation does not evaluate to a single value"
+            ),
+        }
+    }
+}
+
+impl<T: NumericType<ExprType = T>> Add for Term<T> {
+    type Output = Term<T>;
+
+    fn add(self, rhs: Term<T>) -> Term<T> {
+        if self.is(0.0) {
+            rhs
+        } else if rhs.is(0.0) {
+            self
+        } else {
+            Term::call("Add", vec![self, rhs])
+        }
+    }
+}
+
+impl<T: NumericType<ExprType = T>> Sub for Term<T> {
+    type Output = Term<T>;
+
+    fn sub(self, rhs: Term<T>) -> Term<T> {
+        if rhs.is(0.0) {
+            self
+        } else if self.is(0.0) {
+            -rhs
+        } else {
+            Term::call("Subtract", vec![self, rhs])
+        }
+    }
+}
+
+impl<T: NumericType<ExprType = T>> Mul for Term<T> {
+    type Output = Term<T>;
+
+    fn mul(self, rhs: Term<T>) -> Term<T> {
+        if self.is(0.0) || rhs.is(0.0) {
+            Term::number(0.0)
+        } else if self.is(1.0) {
+            rhs
+        } else if rhs.is(1.0) {
+            self
+        } else {
+            Term::call("Multiply", vec![self, rhs])
+        }
+    }
+}
+
+impl<T: NumericType<ExprType = T>> Div for Term<T> {
+    type Output = Term<T>;
+
+    fn div(self, rhs: Term<T>) -> Term<T> {
+        if self.is(0.0) {
+            Term::number(0.0)
+        } else if rhs.is(1.0) {
+            self
+        } else {
+            Term::call("Divide", vec![self, rhs])
+        }
+    }
+}
+
+impl<T: NumericType<ExprType = T>> Neg for Term<T> {
+    type Output = Term<T>;
+
+    fn neg(self) -> Term<T> {
+        match self {
+            Term::Function(label, mut args) if label == "Negate" => args.remove(0),
+            term => Term::call("Negate", vec![term]),
+        }
+    }
+}
+
+impl<T: NumericType> std::fmt::Display for Term<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Term::Number(value) => write!(f, "{}", value),
+            Term::Variable(label) => write!(f, "{}", label),
+            Term::Function(label, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", label, args.join(", "))
+            }
+        }
+    }
+}
@@ -3,9 +3,11 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::rc::Rc;
 
-use crate::expressions::Expression;
+use crate::expressions::{evaluate_expressions, Expression};
 use crate::{
+    calculus::{integrate, Integral, IntegrationOptions, Term},
     error::{return_error, Error, ErrorType},
+    syntax::reference::parse_cell,
     NumericType,
 };
 
@@ -19,7 +21,7 @@ pub struct Equation<T: NumericType> {
     variables: HashMap<String, Rc<RefCell<T>>>,
 }
 
-impl<T: NumericType> Equation<T> {
+impl<T: NumericType<ExprType = T>> Equation<T> {
     pub(crate) fn new(
         data: Vec<Box<dyn Expression<ExprType = T>>>,
         variables: HashMap<String, Rc<RefCell<T>>>,
@@ -37,33 +39,10 @@ impl<T: NumericType> Equation<T> {
             return_error!(ErrorType::NotInitialisedError, "Equation is empty");
         }
 
-        let mut output_stack = Vec::new();
-
-        for expression in &self.data {
-            if expression.num_inputs() > output_stack.len() {
-                return_error!(
-                    ErrorType::SyntaxError,
-                    "Function {} requires {} inputs but the output stack contains {}",
-                    expression,
-                    expression.num_inputs(),
-                    output_stack.len()
-                );
-            }
-            let input_values = output_stack.split_off(output_stack.len() - expression.num_inputs());
-            output_stack.push(expression.evaluate(input_values.as_slice())?);
-        }
-
-        if output_stack.len() != 1 {
-            return_error!(
-                ErrorType::SyntaxError,
-                "Equation does not evaluate to a single value"
-            );
-        }
-
-        return Ok(output_stack[0]);
+        evaluate_expressions(&self.data)
     }
 
-    pub fn variable(&self, label: &str) -> Result<RefMut<T>, Error> {
+    pub fn variable(&self, label: &str) -> Result<RefMut<'_, T>, Error> {
         match self.variables.get(label) {
             Some(var_cell) => match var_cell.try_borrow_mut() {
                 Ok(var) => Ok(var),
@@ -84,6 +63,67 @@ impl<T: NumericType> Equation<T> {
     pub fn variables(&self) -> &[String] {
         &self.variable_names
     }
+
+    /// Sets each variable named like a spreadsheet cell, such as B3, to the value of that cell in a grid of rows,
+    /// e.g. B3 is set to `grid[2][1]`. Other variables are left unchanged.
+    pub fn set_cells(&self, grid: &[Vec<T>]) -> Result<(), Error> {
+        for label in &self.variable_names {
+            if let Some((column, row)) = parse_cell(label) {
+                match grid.get(row).and_then(|cells| cells.get(column)) {
+                    Some(value) => *self.variable(label)? = *value,
+                    None => return_error!(
+                        ErrorType::VariableAccessError,
+                        "Cell '{}' is outside of the grid",
+                        label
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Numerically integrates the equation with respect to a variable between two bounds.
+    /// The variable is set to each sample point in turn and restored to its original value afterwards.
+    pub fn integrate(
+        &self,
+        variable: &str,
+        lower: T,
+        upper: T,
+        options: &IntegrationOptions<T>,
+    ) -> Result<Integral<T>, Error> {
+        let original = *self.variable(variable)?;
+        let result = integrate(
+            |x| {
+                *self.variable(variable)? = x;
+                self.evaluate()
+            },
+            lower,
+            upper,
+            options,
+        );
+        *self.variable(variable)? = original;
+        result
+    }
+
+    /// Differentiates the equation with respect to a variable, giving a new equation of the same variables.
+    /// Variables of the new equation are independent of this one but start with the same values.
+    pub fn derivative(&self, variable: &str) -> Result<Equation<T>, Error> {
+        let derivative = Term::from_expressions(&self.data)?.derivative(variable)?;
+
+        let mut variables: HashMap<String, Rc<RefCell<T>>> = self
+            .variables
+            .iter()
+            .map(|(label, value)| (label.clone(), Rc::new(RefCell::new(*value.borrow()))))
+            .collect();
+        let mut data = Vec::new();
+        derivative.to_expressions(&mut data, &mut variables)?;
+
+        Ok(Equation {
+            data,
+            variable_names: self.variable_names.clone(),
+            variables,
+        })
+    }
 }
 
 impl<T: NumericType> Debug for Equation<T> {
@@ -1,3 +1,6 @@
+use std::fmt;
+use std::ops::Range;
+
 #[derive(Debug)]
 pub enum ErrorType {
     /// Provided syntax file does not exist
@@ -16,18 +19,207 @@ pub enum ErrorType {
     InternalError,
     /// An error occurred registering function bindings
     BindingError,
+    /// Equation contains a function that could not be differentiated
+    DifferentiationError,
+    /// Equation could not be evaluated with the current values, for example a series with infinite bounds
+    EvaluationError,
+    /// Equation was parsed, but part of it is probably not what was meant, e.g. a misspelt function read as a variable
+    Warning,
 }
+
+/// The kind of mistake that caused an error, which stays the same however the message is worded
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// the equation, or a list in prefix notation, has no tokens, e.g. ()
+    EmptyExpression,
+    /// no rule matches the text, e.g. the # of 2 # 3
+    UnknownToken,
+    /// rules match the text but none may appear after the token before it, e.g. the * of 2 + * 3
+    InvalidContext,
+    /// the equation ends with a token that needs something after it, e.g. 2 +
+    UnexpectedEnd,
+    /// a token comes after the end of a complete expression or argument, e.g. the 3 of (+ 1 2) 3
+    UnexpectedToken,
+    /// a closing bracket has no opening bracket, an opening bracket is never closed, or the two do not match
+    UnmatchedBracket,
+    /// a function is missing one of the bracketed groups it takes its arguments from, e.g. \frac{1}
+    MissingArgumentGroup,
+    /// a function or operator is given the wrong number of arguments
+    ArityMismatch,
+    /// a rule or markup refers to a binding that is not registered
+    UnknownBinding,
+    /// the text of a literal is not a valid number
+    InvalidLiteral,
+    /// the text of a cell reference or range is not valid, or a range is used outside a function
+    InvalidReference,
+    /// a program assigns or defines a name it may not, e.g. a reserved name or a function defined twice
+    InvalidAssignment,
+    /// a variable is named almost the same as a function, so is probably the function misspelt, e.g. sinn(x)
+    MisspeltFunction,
+}
+
 #[derive(Debug)]
 pub struct Error {
     pub error_type: ErrorType,
     pub message: String,
+    /// the kind of mistake, if the error is caused by the equation or rules rather than evaluation
+    pub code: Option<ErrorCode>,
+    /// byte offsets of the part of the input the error is about
+    pub span: Option<Range<usize>>,
+    /// further details about the cause of the error
+    pub notes: Vec<String>,
+    /// a suggestion for how to fix the error
+    pub help: Option<String>,
+    /// names of rules or functions that are spelt like the text the error is about, closest first
+    pub suggestions: Vec<String>,
 }
+
 macro_rules! return_error {
     ($error_type:expr, $($message:tt)*) => {
-        return Err(Error {
-            error_type: $error_type,
-            message: format!($($message)*),
-        })
+        return Err(Error::new($error_type, format!($($message)*)))
     };
 }
 pub(crate) use return_error;
+
+impl ErrorCode {
+    /// Name of the code for tools to match on, e.g. "unmatched-bracket"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EmptyExpression => "empty-expression",
+            Self::UnknownToken => "unknown-token",
+            Self::InvalidContext => "invalid-context",
+            Self::UnexpectedEnd => "unexpected-end",
+            Self::UnexpectedToken => "unexpected-token",
+            Self::UnmatchedBracket => "unmatched-bracket",
+            Self::MissingArgumentGroup => "missing-argument-group",
+            Self::ArityMismatch => "arity-mismatch",
+            Self::UnknownBinding => "unknown-binding",
+            Self::InvalidLiteral => "invalid-literal",
+            Self::InvalidReference => "invalid-reference",
+            Self::InvalidAssignment => "invalid-assignment",
+            Self::MisspeltFunction => "misspelt-function",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Error {
+    pub fn new(error_type: ErrorType, message: String) -> Error {
+        Error {
+            error_type,
+            message,
+            code: None,
+            span: None,
+            notes: Vec::new(),
+            help: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Error {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_span(mut self, span: Range<usize>) -> Error {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Error {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Error {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_suggestions(mut self, suggestions: Vec<String>) -> Error {
+        self.suggestions = suggestions;
+        self
+    }
+
+    /// Moves the span by the given number of bytes, for an error found in part of a larger input
+    pub(crate) fn offset_span(mut self, offset: usize) -> Error {
+        self.span = self.span.map(|span| span.start + offset..span.end + offset);
+        self
+    }
+
+    /// Formats the error with the line of the input it is about, underlining its span, e.g.
+    /// ```text
+    /// error[unmatched-bracket]: Unclosed opening bracket
+    ///   |
+    /// 1 | 2 * (3 + 4
+    ///   |     ^
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let severity = match self.error_type {
+            ErrorType::Warning => "warning",
+            _ => "error",
+        };
+        let mut rendered = match self.code {
+            Some(code) => format!("{}[{}]: {}\n", severity, code, self.message),
+            None => format!("{}: {}\n", severity, self.message),
+        };
+        let span = self
+            .span
+            .as_ref()
+            .filter(|span| span.start <= span.end && span.end <= input.len())
+            .filter(|span| input.is_char_boundary(span.start) && input.is_char_boundary(span.end));
+        if let Some(span) = span {
+            let line_start = input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = input[span.start..]
+                .find('\n')
+                .map_or(input.len(), |i| span.start + i);
+            let line = input[line_start..line_end].trim_end_matches('\r');
+            let line_number = (input[..line_start].matches('\n').count() + 1).to_string();
+            let gutter = " ".repeat(line_number.len());
+
+            let column = input[line_start..span.start].chars().count();
+            // spans that run onto the next line are underlined to the end of the first
+            let end = span.end.min(line_start + line.len()).max(span.start);
+            let width = input[span.start..end].chars().count().max(1);
+            rendered.push_str(&format!("{} |\n", gutter));
+            rendered.push_str(&format!("{} | {}\n", line_number, line));
+            rendered.push_str(&format!(
+                "{} | {}{}\n",
+                gutter,
+                " ".repeat(column),
+                "^".repeat(width)
+            ));
+        }
+        for note in &self.notes {
+            rendered.push_str(&format!("  = note: {}\n", note));
+        }
+        if let Some(help) = &self.help {
+            rendered.push_str(&format!("  = help: {}\n", help));
+        }
+        if !self.suggestions.is_empty() {
+            let suggestions: Vec<String> = self
+                .suggestions
+                .iter()
+                .map(|suggestion| format!("'{}'", suggestion))
+                .collect();
+            rendered.push_str(&format!(
+                "  = help: did you mean {}?\n",
+                suggestions.join(" or ")
+            ));
+        }
+        rendered
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
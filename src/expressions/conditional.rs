@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{evaluate_expressions, Expression, Expressions};
+use crate::{
+    calculus::Term,
+    equation::Value,
+    error::{return_error, Error, ErrorType},
+    syntax::ConditionOrder,
+    NumericType,
+};
+
+/// Chooses between values by testing conditions in turn, evaluating only the conditions it needs
+/// and the value it selects, e.g. if(x > 0, x, 0)
+///
+/// Arguments are pairs of a condition and a value, in the order given by `order`, optionally
+/// followed by a single default value. If no condition is true and there is no default, the result is NaN.
+pub struct Conditional<T: NumericType> {
+    order: ConditionOrder,
+    num_arguments: Option<usize>,
+    arguments: Vec<Expressions<T>>,
+}
+
+impl<T: NumericType> Conditional<T> {
+    pub fn new(order: ConditionOrder) -> Conditional<T> {
+        Conditional {
+            order,
+            num_arguments: None,
+            arguments: Vec::new(),
+        }
+    }
+
+    pub fn with_num_arguments(mut self, num_arguments: usize) -> Conditional<T> {
+        self.num_arguments = Some(num_arguments);
+        self
+    }
+}
+
+impl<T: NumericType> Expression for Conditional<T> {
+    type ExprType = T;
+
+    fn evaluate(&self, _values: &[T]) -> Value<T> {
+        if self.arguments.is_empty() {
+            return_error!(
+                ErrorType::SyntaxError,
+                "Conditional must be given its arguments in brackets"
+            );
+        }
+        let (condition, value) = match self.order {
+            ConditionOrder::ConditionFirst => (0, 1),
+            ConditionOrder::ValueFirst => (1, 0),
+        };
+        for pair in self.arguments.chunks(2) {
+            match pair {
+                [default] => return evaluate_expressions(default),
+                _ => {
+                    // zero is false, anything else is true
+                    if !evaluate_expressions(&pair[condition])?.is_zero() {
+                        return evaluate_expressions(&pair[value]);
+                    }
+                }
+            }
+        }
+        Ok(T::nan())
+    }
+
+    fn num_inputs(&self) -> usize {
+        0
+    }
+
+    fn to_term(&self, _inputs: Vec<Term<T>>) -> Result<Term<T>, Error> {
+        return_error!(
+            ErrorType::DifferentiationError,
+            "Conditional {} cannot be converted to a term",
+            self
+        )
+    }
+
+    fn num_captured(&self) -> usize {
+        match self.arguments.is_empty() {
+            true => self.num_arguments.unwrap_or(0),
+            false => 0,
+        }
+    }
+
+    fn capture(&mut self, arguments: Vec<Expressions<T>>) -> Result<(), Error> {
+        if arguments.len() < 2 {
+            return_error!(
+                ErrorType::SyntaxError,
+                "Conditional requires at least 2 arguments but was given {}",
+                arguments.len()
+            );
+        }
+        self.arguments = arguments;
+        Ok(())
+    }
+
+    fn bind_variable(&mut self, label: &str, value: &Rc<RefCell<T>>) {
+        for expression in self.arguments.iter_mut().flatten() {
+            expression.bind_variable(label, value);
+        }
+    }
+
+    fn collect_variables(&self, variables: &mut HashMap<String, Rc<RefCell<T>>>) {
+        for expression in self.arguments.iter().flatten() {
+            expression.collect_variables(variables);
+        }
+    }
+}
+
+impl<T: NumericType> std::fmt::Display for Conditional<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let count = self.num_arguments.unwrap_or(self.arguments.len());
+        write!(f, "Conditional({})", vec!["_"; count].join(", "))
+    }
+}
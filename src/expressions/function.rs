@@ -1,5 +1,6 @@
 use super::Expression;
 use crate::{
+    calculus::{DerivativePointer, Term},
     equation::Value,
     error::{return_error, Error, ErrorType},
     NumericType,
@@ -7,15 +8,59 @@ use crate::{
 
 pub type FunctionPointer<T> = fn(&[T]) -> Value<T>;
 
-pub struct Function<T: 'static> {
+/// The number of arguments a function binding accepts
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Arity {
+    /// exactly this many arguments
+    Exactly(usize),
+    /// this many arguments or more
+    AtLeast(usize),
+    /// between the two numbers of arguments, inclusive
+    Between(usize, usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, num_inputs: usize) -> bool {
+        match *self {
+            Arity::Exactly(n) => num_inputs == n,
+            Arity::AtLeast(min) => num_inputs >= min,
+            Arity::Between(min, max) => num_inputs >= min && num_inputs <= max,
+        }
+    }
+
+    pub fn min(&self) -> usize {
+        match *self {
+            Arity::Exactly(n) | Arity::AtLeast(n) | Arity::Between(n, _) => n,
+        }
+    }
+}
+
+impl std::fmt::Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Arity::Exactly(n) => write!(f, "{}", n),
+            Arity::AtLeast(min) => write!(f, "{} or more", min),
+            Arity::Between(min, max) => write!(f, "{} to {}", min, max),
+        }
+    }
+}
+
+pub struct Function<T: NumericType> {
     pub label: &'static str,
     pub function: &'static FunctionPointer<T>,
+    pub arity: Arity,
+    // number of arguments this function is called with
     pub num_inputs: usize,
+    // partial derivatives with respect to each argument, if the function is differentiable
+    pub derivative: Option<&'static DerivativePointer<T>>,
 }
 
 impl<T: NumericType> Clone for Function<T> {
     fn clone(&self) -> Self {
-        Function::<T>::new(self.label, self.function, self.num_inputs)
+        Function {
+            derivative: self.derivative,
+            ..Function::<T>::new(self.label, self.function, self.arity).with_inputs(self.num_inputs)
+        }
     }
 }
 
@@ -23,21 +68,34 @@ impl<T: NumericType> Function<T> {
     pub fn new(
         label: &'static str,
         function: &'static FunctionPointer<T>,
-        num_inputs: usize,
+        arity: Arity,
     ) -> Function<T> {
         Function {
             label,
             function,
-            num_inputs,
+            arity,
+            num_inputs: arity.min(),
+            derivative: None,
         }
     }
+
+    pub fn with_derivative(mut self, derivative: &'static DerivativePointer<T>) -> Function<T> {
+        self.derivative = Some(derivative);
+        self
+    }
+
+    /// Sets the number of arguments the function is called with
+    pub fn with_inputs(mut self, num_inputs: usize) -> Function<T> {
+        self.num_inputs = num_inputs;
+        self
+    }
 }
 
 impl<T: NumericType> Expression for Function<T> {
     type ExprType = T;
 
     fn evaluate(&self, values: &[T]) -> Value<T> {
-        if values.len() != self.num_inputs {
+        if values.len() != self.num_inputs || !self.arity.accepts(values.len()) {
             return_error!(
                 ErrorType::InternalError,
                 "Tried to call a function with a different number of arguments than expected"
@@ -49,9 +107,13 @@ impl<T: NumericType> Expression for Function<T> {
     fn num_inputs(&self) -> usize {
         self.num_inputs
     }
+
+    fn to_term(&self, inputs: Vec<Term<T>>) -> Result<Term<T>, Error> {
+        Ok(Term::Function(self.label.to_string(), inputs))
+    }
 }
 
-impl<T> std::fmt::Display for Function<T> {
+impl<T: NumericType> std::fmt::Display for Function<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{evaluate_expressions, Expression, Expressions};
+use crate::{
+    calculus::{integrate, IntegrationOptions, Term},
+    equation::Value,
+    error::{return_error, Error, ErrorType},
+    NumericType,
+};
+
+/// Numerically integrates a body with respect to a bound variable between two bounds,
+/// e.g. \int_{0}^{1} x^2 \, dx
+pub struct DefiniteIntegral<T: NumericType> {
+    label: String,
+    // value of the variable of integration, shared only by variables in the body
+    variable: Rc<RefCell<T>>,
+    lower: Expressions<T>,
+    upper: Expressions<T>,
+    body: Expressions<T>,
+}
+
+impl<T: NumericType> DefiniteIntegral<T> {
+    pub fn new() -> DefiniteIntegral<T> {
+        DefiniteIntegral {
+            label: String::new(),
+            variable: Rc::new(RefCell::new(T::zero())),
+            lower: Vec::new(),
+            upper: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+impl<T: NumericType> Expression for DefiniteIntegral<T> {
+    type ExprType = T;
+
+    fn evaluate(&self, _values: &[T]) -> Value<T> {
+        let lower = evaluate_expressions(&self.lower)?;
+        let upper = evaluate_expressions(&self.upper)?;
+        let integral = integrate(
+            |x| {
+                *self.variable.borrow_mut() = x;
+                evaluate_expressions(&self.body)
+            },
+            lower,
+            upper,
+            &IntegrationOptions::default(),
+        )?;
+        Ok(integral.value)
+    }
+
+    fn num_inputs(&self) -> usize {
+        0
+    }
+
+    fn to_term(&self, _inputs: Vec<Term<T>>) -> Result<Term<T>, Error> {
+        return_error!(
+            ErrorType::DifferentiationError,
+            "Integral {} cannot be converted to a term",
+            self
+        )
+    }
+
+    // the lower bound, upper bound, body and variable of integration
+    fn num_captured(&self) -> usize {
+        if self.body.is_empty() {
+            4
+        } else {
+            0
+        }
+    }
+
+    fn capture(&mut self, arguments: Vec<Expressions<T>>) -> Result<(), Error> {
+        let [lower, upper, mut body, variable]: [Expressions<T>; 4] = match arguments.try_into() {
+            Ok(arguments) => arguments,
+            Err(_) => return_error!(
+                ErrorType::InternalError,
+                "Integral requires exactly 4 arguments"
+            ),
+        };
+        self.label = match variable.as_slice() {
+            [variable] => match variable.to_term(Vec::new()) {
+                Ok(Term::Variable(label)) => label,
+                _ => return_error!(
+                    ErrorType::SyntaxError,
+                    "Integral must be with respect to a variable, not '{}'",
+                    variable
+                ),
+            },
+            _ => return_error!(
+                ErrorType::SyntaxError,
+                "Integral must be with respect to a single variable"
+            ),
+        };
+        for expression in body.iter_mut() {
+            expression.bind_variable(&self.label, &self.variable);
+        }
+        self.lower = lower;
+        self.upper = upper;
+        self.body = body;
+        Ok(())
+    }
+
+    fn bind_variable(&mut self, label: &str, value: &Rc<RefCell<T>>) {
+        for expression in self.lower.iter_mut().chain(self.upper.iter_mut()) {
+            expression.bind_variable(label, value);
+        }
+        // the variable of integration hides any variable of the same name in the body
+        if label != self.label {
+            for expression in self.body.iter_mut() {
+                expression.bind_variable(label, value);
+            }
+        }
+    }
+
+    fn collect_variables(&self, variables: &mut HashMap<String, Rc<RefCell<T>>>) {
+        for expression in self.lower.iter().chain(self.upper.iter()) {
+            expression.collect_variables(variables);
+        }
+        let mut body_variables = HashMap::new();
+        for expression in &self.body {
+            expression.collect_variables(&mut body_variables);
+        }
+        body_variables.remove(&self.label);
+        variables.extend(body_variables);
+    }
+}
+
+impl<T: NumericType> std::fmt::Display for DefiniteIntegral<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Integral(_, _, _, d{})", self.label)
+    }
+}
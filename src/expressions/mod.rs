@@ -1,13 +1,117 @@
+pub(crate) mod conditional;
 pub(crate) mod function;
+pub(crate) mod integral;
 pub(crate) mod number;
+pub(crate) mod series;
+pub(crate) mod user_function;
 pub(crate) mod variable;
 
-use crate::equation::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{
+    calculus::Term,
+    equation::Value,
+    error::{return_error, Error, ErrorType},
+    NumericType,
+};
+
+pub type Expressions<T> = Vec<Box<dyn Expression<ExprType = T>>>;
 
 pub trait Expression: std::fmt::Display {
-    type ExprType;
+    type ExprType: NumericType;
 
     fn evaluate(&self, values: &[Self::ExprType]) -> Value<Self::ExprType>;
 
     fn num_inputs(&self) -> usize;
+
+    /// Builds a symbolic term from this expression applied to its inputs
+    fn to_term(&self, inputs: Vec<Term<Self::ExprType>>) -> Result<Term<Self::ExprType>, Error>;
+
+    /// Number of preceding arguments this expression takes unevaluated, such as the body of a summation
+    fn num_captured(&self) -> usize {
+        0
+    }
+
+    /// Gives the expression the arguments it takes unevaluated, each as a list of expressions
+    fn capture(&mut self, _arguments: Vec<Expressions<Self::ExprType>>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Makes any variable with the given label use a different value
+    fn bind_variable(&mut self, _label: &str, _value: &Rc<RefCell<Self::ExprType>>) {}
+
+    /// Adds the free variables used by this expression to the map
+    fn collect_variables(&self, _variables: &mut HashMap<String, Rc<RefCell<Self::ExprType>>>) {}
+}
+
+/// Evaluates a list of expressions left to right, giving the single value left at the end
+pub(crate) fn evaluate_expressions<T: NumericType>(
+    data: &[Box<dyn Expression<ExprType = T>>],
+) -> Value<T> {
+    let mut output_stack = Vec::new();
+
+    for expression in data {
+        if expression.num_inputs() > output_stack.len() {
+            return_error!(
+                ErrorType::SyntaxError,
+                "Function {} requires {} inputs but the output stack contains {}",
+                expression,
+                expression.num_inputs(),
+                output_stack.len()
+            );
+        }
+        let input_values = output_stack.split_off(output_stack.len() - expression.num_inputs());
+        output_stack.push(expression.evaluate(input_values.as_slice())?);
+    }
+
+    if output_stack.len() != 1 {
+        return_error!(
+            ErrorType::SyntaxError,
+            "Equation does not evaluate to a single value"
+        );
+    }
+
+    Ok(output_stack[0])
+}
+
+/// Gives each expression that takes unevaluated arguments the expressions of those arguments from before it
+pub(crate) fn capture_arguments<T: NumericType>(
+    data: Expressions<T>,
+) -> Result<Expressions<T>, Error> {
+    let mut output: Expressions<T> = Vec::with_capacity(data.len());
+    for mut expression in data {
+        let num_captured = expression.num_captured();
+        if num_captured > 0 {
+            let mut arguments = Vec::with_capacity(num_captured);
+            for _ in 0..num_captured {
+                arguments.push(split_last_argument(&mut output, &*expression)?);
+            }
+            arguments.reverse();
+            expression.capture(arguments)?;
+        }
+        output.push(expression);
+    }
+    Ok(output)
+}
+
+/// Removes the expressions that make up the last complete value from the end of the list
+fn split_last_argument<T: NumericType>(
+    data: &mut Expressions<T>,
+    function: &dyn Expression<ExprType = T>,
+) -> Result<Expressions<T>, Error> {
+    let mut needed = 1;
+    for start in (0..data.len()).rev() {
+        needed = needed + data[start].num_inputs() - 1;
+        if needed == 0 {
+            return Ok(data.split_off(start));
+        }
+    }
+    return_error!(
+        ErrorType::SyntaxError,
+        "Function {} requires {} arguments but was given fewer",
+        function,
+        function.num_captured()
+    )
 }
@@ -1,5 +1,5 @@
 use super::Expression;
-use crate::{equation::Value, NumericType};
+use crate::{calculus::Term, equation::Value, error::Error, NumericType};
 
 pub struct Number<T: NumericType> {
     value: T,
@@ -21,6 +21,10 @@ impl<T: NumericType> Expression for Number<T> {
     fn num_inputs(&self) -> usize {
         0
     }
+
+    fn to_term(&self, _inputs: Vec<Term<T>>) -> Result<Term<T>, Error> {
+        Ok(Term::Number(self.value))
+    }
 }
 
 impl<T: NumericType> std::fmt::Display for Number<T> {
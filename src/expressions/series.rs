@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{evaluate_expressions, Expression, Expressions};
+use crate::{
+    calculus::Term,
+    equation::Value,
+    error::{return_error, Error, ErrorType},
+    expressions::function::Function,
+    NumericType,
+};
+
+/// Evaluates a body once for each integer step of a bound index variable between two bounds,
+/// combining the results with a function binding, e.g. the sum in \sum_{i=1}^{n} i^2
+pub struct Series<T: NumericType> {
+    binding: Function<T>,
+    label: String,
+    // value of the index variable, shared only by variables in the body
+    index: Rc<RefCell<T>>,
+    lower: Expressions<T>,
+    upper: Expressions<T>,
+    body: Expressions<T>,
+}
+
+impl<T: NumericType> Series<T> {
+    pub fn new(binding: Function<T>) -> Series<T> {
+        Series {
+            binding,
+            label: String::new(),
+            index: Rc::new(RefCell::new(T::zero())),
+            lower: Vec::new(),
+            upper: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+impl<T: NumericType> Expression for Series<T> {
+    type ExprType = T;
+
+    fn evaluate(&self, _values: &[T]) -> Value<T> {
+        let lower = evaluate_expressions(&self.lower)?;
+        let upper = evaluate_expressions(&self.upper)?;
+        if !lower.is_finite() || !upper.is_finite() {
+            return_error!(
+                ErrorType::EvaluationError,
+                "Bounds of {} must be finite but were {} and {}",
+                self,
+                lower,
+                upper
+            );
+        }
+        let mut values = Vec::new();
+        let mut index = lower;
+        while index <= upper {
+            *self.index.borrow_mut() = index;
+            values.push(evaluate_expressions(&self.body)?);
+            if index + T::one() == index {
+                return_error!(
+                    ErrorType::EvaluationError,
+                    "Index of {} is too large to be incremented",
+                    self
+                );
+            }
+            index = index + T::one();
+        }
+        if !self.binding.arity.accepts(values.len()) {
+            return_error!(
+                ErrorType::EvaluationError,
+                "Function '{}' takes {} arguments but the range of {} has {} values",
+                self.binding.label,
+                self.binding.arity,
+                self,
+                values.len()
+            );
+        }
+        (self.binding.function)(&values)
+    }
+
+    fn num_inputs(&self) -> usize {
+        0
+    }
+
+    fn to_term(&self, _inputs: Vec<Term<T>>) -> Result<Term<T>, Error> {
+        return_error!(
+            ErrorType::DifferentiationError,
+            "Series {} cannot be converted to a term",
+            self
+        )
+    }
+
+    // the index variable, lower bound, upper bound and body
+    fn num_captured(&self) -> usize {
+        if self.body.is_empty() {
+            4
+        } else {
+            0
+        }
+    }
+
+    fn capture(&mut self, arguments: Vec<Expressions<T>>) -> Result<(), Error> {
+        let [index, lower, upper, mut body]: [Expressions<T>; 4] = match arguments.try_into() {
+            Ok(arguments) => arguments,
+            Err(_) => return_error!(
+                ErrorType::InternalError,
+                "Series requires exactly 4 arguments"
+            ),
+        };
+        self.label = match index.as_slice() {
+            [variable] => match variable.to_term(Vec::new()) {
+                Ok(Term::Variable(label)) => label,
+                _ => return_error!(
+                    ErrorType::SyntaxError,
+                    "Index of {} must be a variable, not '{}'",
+                    self,
+                    variable
+                ),
+            },
+            _ => return_error!(
+                ErrorType::SyntaxError,
+                "Index of {} must be a single variable",
+                self
+            ),
+        };
+        for expression in body.iter_mut() {
+            expression.bind_variable(&self.label, &self.index);
+        }
+        self.lower = lower;
+        self.upper = upper;
+        self.body = body;
+        Ok(())
+    }
+
+    fn bind_variable(&mut self, label: &str, value: &Rc<RefCell<T>>) {
+        for expression in self.lower.iter_mut().chain(self.upper.iter_mut()) {
+            expression.bind_variable(label, value);
+        }
+        // the index variable hides any variable of the same name in the body
+        if label != self.label {
+            for expression in self.body.iter_mut() {
+                expression.bind_variable(label, value);
+            }
+        }
+    }
+
+    fn collect_variables(&self, variables: &mut HashMap<String, Rc<RefCell<T>>>) {
+        for expression in self.lower.iter().chain(self.upper.iter()) {
+            expression.collect_variables(variables);
+        }
+        let mut body_variables = HashMap::new();
+        for expression in &self.body {
+            expression.collect_variables(&mut body_variables);
+        }
+        body_variables.remove(&self.label);
+        variables.extend(body_variables);
+    }
+}
+
+impl<T: NumericType> std::fmt::Display for Series<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}(_, _, _, _)", self.binding.label)
+    }
+}
@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{evaluate_expressions, Expression, Expressions};
+use crate::{
+    calculus::Term,
+    equation::Value,
+    error::{return_error, Error, ErrorType},
+    NumericType,
+};
+
+/// A function defined by a statement of a program, e.g. the f in f(t) = t^2 + 1
+pub struct UserFunction<T: NumericType> {
+    pub label: String,
+    // labels and values of the parameters, shared only by variables in the body
+    parameters: Vec<(String, Rc<RefCell<T>>)>,
+    body: Expressions<T>,
+}
+
+impl<T: NumericType<ExprType = T>> UserFunction<T> {
+    pub fn new(
+        label: &str,
+        parameters: Vec<(String, Rc<RefCell<T>>)>,
+        body: Expressions<T>,
+    ) -> UserFunction<T> {
+        UserFunction {
+            label: label.to_string(),
+            parameters,
+            body,
+        }
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// Adds the variables used by the body that are not parameters to the map
+    pub fn collect_variables(&self, variables: &mut HashMap<String, Rc<RefCell<T>>>) {
+        let mut body_variables = HashMap::new();
+        for expression in &self.body {
+            expression.collect_variables(&mut body_variables);
+        }
+        for (label, _) in &self.parameters {
+            body_variables.remove(label);
+        }
+        variables.extend(body_variables);
+    }
+}
+
+/// A call to a user-defined function, which evaluates its body with the parameters set to the inputs
+pub struct UserFunctionCall<T: NumericType> {
+    definition: Rc<UserFunction<T>>,
+}
+
+impl<T: NumericType> UserFunctionCall<T> {
+    pub fn new(definition: &Rc<UserFunction<T>>) -> UserFunctionCall<T> {
+        UserFunctionCall {
+            definition: Rc::clone(definition),
+        }
+    }
+}
+
+impl<T: NumericType<ExprType = T>> Expression for UserFunctionCall<T> {
+    type ExprType = T;
+
+    fn evaluate(&self, values: &[T]) -> Value<T> {
+        // arguments are evaluated before the call, so a call inside the arguments of another
+        // call to the same function has finished with the parameters by the time they are set
+        for ((label, parameter), value) in self.definition.parameters.iter().zip(values) {
+            match parameter.try_borrow_mut() {
+                Ok(mut parameter) => *parameter = *value,
+                Err(..) => return_error!(
+                    ErrorType::VariableAccessError,
+                    "Parameter '{}' of function '{}' already mutably borrowed",
+                    label,
+                    self.definition.label
+                ),
+            }
+        }
+        evaluate_expressions(&self.definition.body)
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.definition.num_parameters()
+    }
+
+    fn to_term(&self, inputs: Vec<Term<T>>) -> Result<Term<T>, Error> {
+        let body = Term::from_expressions(&self.definition.body)?;
+        let arguments = self
+            .definition
+            .parameters
+            .iter()
+            .map(|(label, _)| label.clone())
+            .zip(inputs)
+            .collect();
+        Ok(body.substitute(&arguments))
+    }
+
+    fn collect_variables(&self, variables: &mut HashMap<String, Rc<RefCell<T>>>) {
+        self.definition.collect_variables(variables);
+    }
+}
+
+impl<T: NumericType> std::fmt::Display for UserFunctionCall<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.definition.label)
+    }
+}
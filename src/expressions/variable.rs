@@ -1,7 +1,7 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use super::Expression;
-use crate::{equation::Value, NumericType};
+use crate::{calculus::Term, equation::Value, error::Error, NumericType};
 
 pub struct Variable<T: NumericType> {
     label: String,
@@ -21,12 +21,28 @@ impl<T: NumericType> Expression for Variable<T> {
     type ExprType = T;
 
     fn evaluate(&self, _values: &[T]) -> Value<T> {
-        Ok(self.value.borrow().clone())
+        Ok(*self.value.borrow())
     }
 
     fn num_inputs(&self) -> usize {
         0
     }
+
+    fn to_term(&self, _inputs: Vec<Term<T>>) -> Result<Term<T>, Error> {
+        Ok(Term::Variable(self.label.clone()))
+    }
+
+    fn bind_variable(&mut self, label: &str, value: &Rc<RefCell<T>>) {
+        if self.label == label {
+            self.value = Rc::clone(value);
+        }
+    }
+
+    fn collect_variables(&self, variables: &mut HashMap<String, Rc<RefCell<T>>>) {
+        variables
+            .entry(self.label.clone())
+            .or_insert_with(|| Rc::clone(&self.value));
+    }
 }
 
 impl<T: NumericType> std::fmt::Display for Variable<T> {
@@ -1,9 +1,17 @@
+// errors carry their span, notes, help and suggestions by value, so are larger than clippy's limit
+#![allow(clippy::result_large_err)]
+
 pub mod bindings;
+pub mod calculus;
 mod equation;
 mod error;
 mod expressions;
+pub mod mathml;
 mod parser;
+mod program;
+mod report;
 pub mod syntax;
+mod token;
 
 use std::fmt::Display;
 
@@ -15,8 +23,20 @@ pub use parser::Parser;
  * A structure representing a mathematical function of a number of variables
  */
 pub use equation::{Equation, Value};
+/*
+ * A list of statements that assign values to variables used by later statements
+ */
+pub use program::Program;
+/*
+ * A piece of an equation matched by a syntax rule, with its position in the equation
+ */
+pub use token::Token;
+/*
+ * Every error found in an equation, with as much of the equation as could still be parsed
+ */
+pub use report::{ParseReport, Repair};
 
-pub use error::{Error, ErrorType};
+pub use error::{Error, ErrorCode, ErrorType};
 
 // define constraint for the type of value used by an Equation
 pub trait NumericType:
@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    calculus::Term,
+    error::{return_error, Error, ErrorType},
+    Equation, NumericType,
+};
+
+// named entities commonly written in MathML, which XML does not define without a DTD
+const ENTITIES: &[(&str, &str)] = &[
+    ("&ApplyFunction;", "&#x2061;"),
+    ("&af;", "&#x2061;"),
+    ("&InvisibleTimes;", "&#x2062;"),
+    ("&it;", "&#x2062;"),
+    ("&InvisibleComma;", "&#x2063;"),
+    ("&ic;", "&#x2063;"),
+    ("&minus;", "&#x2212;"),
+    ("&times;", "&#xD7;"),
+    ("&divide;", "&#xF7;"),
+    ("&sdot;", "&#x22C5;"),
+    ("&middot;", "&#xB7;"),
+    ("&le;", "&#x2264;"),
+    ("&ge;", "&#x2265;"),
+    ("&ne;", "&#x2260;"),
+    ("&pi;", "&#x3C0;"),
+    ("&ExponentialE;", "&#x2147;"),
+    ("&ee;", "&#x2147;"),
+];
+
+const APPLY_FUNCTION: &str = "\u{2061}";
+const INVISIBLE_TIMES: &str = "\u{2062}";
+const INVISIBLE_COMMA: &str = "\u{2063}";
+
+// function names written as <mi> in presentation markup and as empty elements in content markup
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("sin", "Sine"),
+    ("cos", "Cosine"),
+    ("tan", "Tangent"),
+    ("sec", "Secant"),
+    ("csc", "Cosecant"),
+    ("cot", "Cotangent"),
+    ("arcsin", "Arcsine"),
+    ("arccos", "Arccosine"),
+    ("arctan", "Arctangent"),
+    ("sinh", "HypSine"),
+    ("cosh", "HypCosine"),
+    ("tanh", "HypTangent"),
+    ("arcsinh", "InvHypSine"),
+    ("arccosh", "InvHypCosine"),
+    ("arctanh", "InvHypTangent"),
+    ("ln", "LogBaseE"),
+    ("log", "LogBase10"),
+    ("abs", "Absolute"),
+    ("floor", "Floor"),
+    ("ceiling", "Ceiling"),
+    ("min", "Min"),
+    ("max", "Max"),
+    ("mean", "Mean"),
+    ("factorial", "Factorial"),
+    ("not", "Not"),
+];
+
+const CONSTANTS: &[(&str, &str)] = &[
+    ("π", "Pi"),
+    ("e", "Euler"),
+    ("ⅇ", "Euler"),
+    ("pi", "Pi"),
+    ("exponentiale", "Euler"),
+    ("true", "True"),
+    ("false", "False"),
+];
+
+// infix operators written as <mo>, with the same precedences as the standard syntax
+const OPERATORS: &[(&str, &str, u32)] = &[
+    ("∨", "Or", 1),
+    ("∧", "And", 2),
+    ("=", "Equal", 3),
+    ("≠", "NotEqual", 3),
+    ("<", "LessThan", 3),
+    ("≤", "LessThanOrEqual", 3),
+    (">", "GreaterThan", 3),
+    ("≥", "GreaterThanOrEqual", 3),
+    ("+", "Add", 4),
+    ("-", "Subtract", 4),
+    ("−", "Subtract", 4),
+    ("*", "Multiply", 5),
+    ("×", "Multiply", 5),
+    ("·", "Multiply", 5),
+    ("⋅", "Multiply", 5),
+    ("∗", "Multiply", 5),
+    (INVISIBLE_TIMES, "Multiply", 5),
+    ("/", "Divide", 5),
+    ("÷", "Divide", 5),
+    ("∕", "Divide", 5),
+    ("mod", "Modulo", 5),
+];
+
+// operators in content markup that fold over any number of arguments
+const CONTENT_FOLDS: &[(&str, &str)] = &[
+    ("plus", "Add"),
+    ("times", "Multiply"),
+    ("and", "And"),
+    ("or", "Or"),
+];
+
+const CONTENT_OPERATORS: &[(&str, &str)] = &[
+    ("divide", "Divide"),
+    ("power", "Exponent"),
+    ("rem", "Modulo"),
+    ("eq", "Equal"),
+    ("neq", "NotEqual"),
+    ("lt", "LessThan"),
+    ("leq", "LessThanOrEqual"),
+    ("gt", "GreaterThan"),
+    ("geq", "GreaterThanOrEqual"),
+];
+
+const UNARY_PRECEDENCE: u32 = 6;
+
+/// Builds an Equation from a `<math>` element containing presentation or content MathML.
+///
+/// Operators are mapped onto function binding labels such as "Divide", "SquareRoot" and "Exponent", so custom
+/// bindings registered under those labels are used. Functions applied with `&ApplyFunction;` or `<csymbol>` use
+/// their name as the binding label.
+pub fn parse<T: NumericType<ExprType = T>>(markup: &str) -> Result<Equation<T>, Error> {
+    let mut markup = markup.to_string();
+    for (entity, reference) in ENTITIES {
+        markup = markup.replace(entity, reference);
+    }
+    let document = match Document::parse(&markup) {
+        Ok(document) => document,
+        Err(e) => return_error!(ErrorType::SyntaxError, "MathML is not valid XML: {}", e),
+    };
+    let root = document.root_element();
+    if root.tag_name().name() != "math" {
+        return_error!(
+            ErrorType::SyntaxError,
+            "Expected root element <math> but found <{}>",
+            root.tag_name().name()
+        )
+    }
+
+    let term: Term<T> = parse_row(root)?;
+    let mut expressions = Vec::new();
+    let mut variables = HashMap::new();
+    term.to_expressions(&mut expressions, &mut variables)?;
+    Ok(Equation::new(expressions, variables))
+}
+
+fn lookup<'a>(table: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    table
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, label)| *label)
+}
+
+fn call<T: NumericType>(label: &str, args: Vec<Term<T>>) -> Term<T> {
+    Term::Function(label.to_string(), args)
+}
+
+fn elements<'a, 'input>(node: Node<'a, 'input>) -> Vec<Node<'a, 'input>> {
+    node.children().filter(|child| child.is_element()).collect()
+}
+
+fn text(node: Node) -> String {
+    node.descendants()
+        .filter(|child| child.is_text())
+        .filter_map(|child| child.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn parse_number<T: NumericType>(node: Node) -> Result<Term<T>, Error> {
+    let value = text(node);
+    match value.parse::<T>() {
+        Ok(n) => Ok(Term::Number(n)),
+        Err(_) => return_error!(ErrorType::SyntaxError, "'{}' is not a valid number", value),
+    }
+}
+
+/// Converts a single element into a term
+fn parse_element<T: NumericType<ExprType = T>>(node: Node) -> Result<Term<T>, Error> {
+    let children = elements(node);
+    let expect_children = |n: usize| -> Result<(), Error> {
+        if children.len() != n {
+            return_error!(
+                ErrorType::SyntaxError,
+                "<{}> requires {} children but has {}",
+                node.tag_name().name(),
+                n,
+                children.len()
+            )
+        }
+        Ok(())
+    };
+    match node.tag_name().name() {
+        "math" | "mrow" | "mstyle" | "mpadded" | "msqrt" | "mtd" => {
+            let row = parse_row(node)?;
+            Ok(match node.tag_name().name() {
+                "msqrt" => call("SquareRoot", vec![row]),
+                _ => row,
+            })
+        }
+        "semantics" => match children.first() {
+            Some(child) => parse_element(*child),
+            None => return_error!(ErrorType::SyntaxError, "<semantics> is empty"),
+        },
+        "mn" | "cn" => parse_number(node),
+        "mi" | "ci" => {
+            let name = text(node);
+            match lookup(CONSTANTS, &name) {
+                Some(label) if node.tag_name().name() == "mi" => Ok(call(label, vec![])),
+                _ => Ok(Term::Variable(name)),
+            }
+        }
+        "mfrac" => {
+            expect_children(2)?;
+            Ok(call(
+                "Divide",
+                vec![parse_element(children[0])?, parse_element(children[1])?],
+            ))
+        }
+        "msup" => {
+            expect_children(2)?;
+            Ok(call(
+                "Exponent",
+                vec![parse_element(children[0])?, parse_element(children[1])?],
+            ))
+        }
+        // the index of a root comes second in MathML but first in the binding
+        "mroot" => {
+            expect_children(2)?;
+            Ok(call(
+                "Root",
+                vec![parse_element(children[1])?, parse_element(children[0])?],
+            ))
+        }
+        // subscripted identifiers are variables, e.g. x_1
+        "msub" => {
+            expect_children(2)?;
+            match (children[0].tag_name().name(), children[1].tag_name().name()) {
+                ("mi", "mi" | "mn") => Ok(Term::Variable(format!(
+                    "{}_{}",
+                    text(children[0]),
+                    text(children[1])
+                ))),
+                _ => return_error!(
+                    ErrorType::SyntaxError,
+                    "<msub> is only supported for subscripted identifiers"
+                ),
+            }
+        }
+        "mfenced" => {
+            let mut args = parse_fenced(node)?;
+            match args.len() {
+                1 => Ok(args.remove(0)),
+                n => return_error!(
+                    ErrorType::SyntaxError,
+                    "<mfenced> with {} arguments must follow a function",
+                    n
+                ),
+            }
+        }
+        "apply" => parse_apply(node),
+        "csymbol" => Ok(call(&text(node), vec![])),
+        name => match lookup(CONSTANTS, name) {
+            Some(label) => Ok(call(label, vec![])),
+            None => return_error!(
+                ErrorType::SyntaxError,
+                "Unsupported MathML element <{}>",
+                name
+            ),
+        },
+    }
+}
+
+fn parse_fenced<T: NumericType<ExprType = T>>(node: Node) -> Result<Vec<Term<T>>, Error> {
+    elements(node).into_iter().map(parse_element).collect()
+}
+
+/// Converts a content MathML `<apply>` element, whose first child is the operator
+fn parse_apply<T: NumericType<ExprType = T>>(node: Node) -> Result<Term<T>, Error> {
+    let children = elements(node);
+    let operator = match children.first() {
+        Some(child) => *child,
+        None => return_error!(ErrorType::SyntaxError, "<apply> is empty"),
+    };
+    // qualifiers such as <degree> and <logbase> are given as children alongside the arguments
+    let qualifier = |name: &str| -> Result<Option<Term<T>>, Error> {
+        match children[1..]
+            .iter()
+            .find(|child| child.tag_name().name() == name)
+        {
+            Some(child) => Ok(Some(parse_row(*child)?)),
+            None => Ok(None),
+        }
+    };
+    let mut args = children[1..]
+        .iter()
+        .filter(|child| !matches!(child.tag_name().name(), "degree" | "logbase"))
+        .map(|child| parse_element(*child))
+        .collect::<Result<Vec<Term<T>>, Error>>()?;
+
+    let name = operator.tag_name().name();
+    if let Some(label) = lookup(CONTENT_FOLDS, name) {
+        if args.is_empty() {
+            return_error!(ErrorType::SyntaxError, "<{}/> requires arguments", name)
+        }
+        let first = args.remove(0);
+        return Ok(args
+            .into_iter()
+            .fold(first, |left, right| call(label, vec![left, right])));
+    }
+    Ok(match name {
+        "minus" if args.len() == 1 => call("Negate", args),
+        "minus" => call("Subtract", args),
+        "root" => match qualifier("degree")? {
+            Some(degree) => call("Root", [vec![degree], args].concat()),
+            None => call("SquareRoot", args),
+        },
+        "log" => match qualifier("logbase")? {
+            Some(base) => call("Logarithm", [vec![base], args].concat()),
+            None => call("LogBase10", args),
+        },
+        "exp" => call("Exponent", [vec![call("Euler", vec![])], args].concat()),
+        // custom functions are named by a symbol or identifier, which is used as the binding label
+        "csymbol" | "ci" => call(&text(operator), args),
+        name => match lookup(CONTENT_OPERATORS, name).or_else(|| lookup(FUNCTIONS, name)) {
+            Some(label) => call(label, args),
+            None => return_error!(
+                ErrorType::SyntaxError,
+                "Unsupported MathML operator <{}/>",
+                name
+            ),
+        },
+    })
+}
+
+enum Item<T: NumericType> {
+    Operand(Term<T>),
+    Operator(String),
+    Function(String),
+    Arguments(Vec<Term<T>>),
+}
+
+/// Converts the children of a presentation element into a term, parsing `<mo>` operators by precedence
+fn parse_row<T: NumericType<ExprType = T>>(node: Node) -> Result<Term<T>, Error> {
+    let children = elements(node);
+    let mut items = Vec::new();
+    for (i, child) in children.iter().enumerate() {
+        let applied = children
+            .get(i + 1)
+            .is_some_and(|next| next.tag_name().name() == "mo" && text(*next) == APPLY_FUNCTION);
+        match child.tag_name().name() {
+            "mo" => match text(*child).as_str() {
+                APPLY_FUNCTION => (),
+                INVISIBLE_COMMA => items.push(Item::Operator(",".to_string())),
+                op => items.push(Item::Operator(op.to_string())),
+            },
+            "mi" => {
+                let name = text(*child);
+                match lookup(FUNCTIONS, &name) {
+                    Some(label) => items.push(Item::Function(label.to_string())),
+                    None if applied => items.push(Item::Function(name)),
+                    None => items.push(Item::Operand(parse_element(*child)?)),
+                }
+            }
+            "mfenced" => items.push(Item::Arguments(parse_fenced(*child)?)),
+            "mspace" | "mtext" | "annotation" | "annotation-xml" => (),
+            _ => items.push(Item::Operand(parse_element(*child)?)),
+        }
+    }
+    let mut row = Row { items, position: 0 };
+    let term = row.expression(0)?;
+    match row.items.get(row.position) {
+        None => Ok(term),
+        Some(Item::Operator(op)) => {
+            return_error!(ErrorType::SyntaxError, "Unexpected operator '{}'", op)
+        }
+        Some(_) => return_error!(ErrorType::SyntaxError, "Unexpected item in <mrow>"),
+    }
+}
+
+struct Row<T: NumericType> {
+    items: Vec<Item<T>>,
+    position: usize,
+}
+
+impl<T: NumericType<ExprType = T>> Row<T> {
+    fn peek_operator(&self) -> Option<&str> {
+        match self.items.get(self.position) {
+            Some(Item::Operator(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect_operator(&mut self, expected: &str) -> Result<(), Error> {
+        match self.peek_operator() {
+            Some(op) if op == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            _ => return_error!(ErrorType::SyntaxError, "Expected '{}'", expected),
+        }
+    }
+
+    /// Parses operands joined by operators binding at least as tightly as `min_precedence`
+    fn expression(&mut self, min_precedence: u32) -> Result<Term<T>, Error> {
+        let mut left = self.unary()?;
+        loop {
+            let binary = self
+                .peek_operator()
+                .and_then(|op| OPERATORS.iter().find(|(symbol, _, _)| *symbol == op));
+            match (binary, self.items.get(self.position)) {
+                (Some((_, label, precedence)), _) if *precedence >= min_precedence => {
+                    self.position += 1;
+                    let right = self.expression(precedence + 1)?;
+                    left = call(label, vec![left, right]);
+                }
+                (None, Some(Item::Operator(op))) if op == "!" => {
+                    self.position += 1;
+                    left = call("Factorial", vec![left]);
+                }
+                // adjacent operands are multiplied, e.g. 2x
+                (None, Some(Item::Operand(_) | Item::Function(_)))
+                | (None, Some(Item::Arguments(_)))
+                    if min_precedence <= 5 =>
+                {
+                    let right = self.expression(6)?;
+                    left = call("Multiply", vec![left, right]);
+                }
+                (None, Some(Item::Operator(op))) if op == "(" && min_precedence <= 5 => {
+                    let right = self.expression(6)?;
+                    left = call("Multiply", vec![left, right]);
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Term<T>, Error> {
+        let item = match self.items.get_mut(self.position) {
+            Some(item) => std::mem::replace(item, Item::Arguments(Vec::new())),
+            None => return_error!(ErrorType::SyntaxError, "Expected an operand"),
+        };
+        self.position += 1;
+        match item {
+            Item::Operand(term) => Ok(term),
+            Item::Arguments(mut args) if args.len() == 1 => Ok(args.remove(0)),
+            Item::Arguments(_) => {
+                return_error!(
+                    ErrorType::SyntaxError,
+                    "Argument lists must follow a function"
+                )
+            }
+            Item::Function(label) => {
+                let args = match self.items.get_mut(self.position) {
+                    Some(Item::Arguments(args)) => {
+                        let args = std::mem::take(args);
+                        self.position += 1;
+                        args
+                    }
+                    Some(Item::Operator(op)) if op == "(" => self.arguments()?,
+                    _ => vec![self.expression(UNARY_PRECEDENCE)?],
+                };
+                Ok(call(&label, args))
+            }
+            Item::Operator(op) => match op.as_str() {
+                "-" | "−" => Ok(call("Negate", vec![self.expression(UNARY_PRECEDENCE)?])),
+                "+" => Ok(call("Identity", vec![self.expression(UNARY_PRECEDENCE)?])),
+                "¬" => Ok(call("Not", vec![self.expression(UNARY_PRECEDENCE)?])),
+                "(" | "[" | "{" => {
+                    let close = match op.as_str() {
+                        "(" => ")",
+                        "[" => "]",
+                        _ => "}",
+                    };
+                    let term = self.expression(0)?;
+                    self.expect_operator(close)?;
+                    Ok(term)
+                }
+                "|" => {
+                    let term = self.expression(0)?;
+                    self.expect_operator("|")?;
+                    Ok(call("Absolute", vec![term]))
+                }
+                _ => return_error!(ErrorType::SyntaxError, "Unexpected operator '{}'", op),
+            },
+        }
+    }
+
+    /// Parses a bracketed, comma separated argument list following a function
+    fn arguments(&mut self) -> Result<Vec<Term<T>>, Error> {
+        self.expect_operator("(")?;
+        let mut args = vec![self.expression(0)?];
+        while self.peek_operator() == Some(",") {
+            self.position += 1;
+            args.push(self.expression(0)?);
+        }
+        self.expect_operator(")")?;
+        Ok(args)
+    }
+}
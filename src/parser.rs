@@ -1,15 +1,35 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::rc::Rc;
 
 use crate::equation::Equation;
+use crate::program::{Program, Statement};
+use crate::report::{ParseReport, Repair};
 use crate::syntax::ruleset::{rule::Rule, Ruleset};
-use crate::syntax::{get_builtin_ruleset, Category, RuleCollectionDefinition, Syntax};
+use crate::syntax::{
+    get_builtin_notation, get_builtin_ruleset, ArgumentDefinition, Category, Notation, Reference,
+    RuleCollectionDefinition, Syntax,
+};
+use crate::token::Token;
 use crate::{
-    error::{return_error, Error, ErrorType},
-    expressions::{number::Number, variable::Variable, Expression},
+    error::{return_error, Error, ErrorCode, ErrorType},
+    expressions::{
+        capture_arguments,
+        conditional::Conditional,
+        evaluate_expressions,
+        function::Arity,
+        integral::DefiniteIntegral,
+        number::Number,
+        series::Series,
+        user_function::{UserFunction, UserFunctionCall},
+        variable::Variable,
+        Expression, Expressions,
+    },
     NumericType,
 };
+use regex::Regex;
 
 macro_rules! syntax_error {
     ($($t:tt)*) => {
@@ -17,8 +37,332 @@ macro_rules! syntax_error {
     };
 }
 
+/// Returns a syntax error about the given byte range of the equation, with the code of the mistake
+macro_rules! spanned_error {
+    ($code:expr, $span:expr, $($t:tt)*) => {
+        return Err(Error::new(ErrorType::SyntaxError, format!($($t)*))
+            .with_code($code)
+            .with_span($span))
+    };
+}
+
+/// An entry on the operator stack of the shunting-yard algorithm
+struct Operation<'a, T: NumericType> {
+    rule: &'a Rule<T>,
+    expression: Option<Box<dyn Expression<ExprType = T>>>,
+    // index of the next argument group a capturing function expects, e.g. 1 after the {a} of \frac{a}{b}
+    next_group: usize,
+    // number of comma-separated arguments seen so far inside a bracket
+    arguments: usize,
+    // number of arguments supplied by the argument groups a capturing function has closed
+    captured: usize,
+    // text that separates arguments inside an argument group bracket, e.g. the = in \sum_{i=1}
+    separator: Option<&'a str>,
+    // whether a capturing function has all of its groups and is waiting for its operand
+    operand_pending: bool,
+    // byte offsets of the token in the equation, for errors about the operation
+    span: Range<usize>,
+}
+
+impl<'a, T: NumericType> Operation<'a, T> {
+    fn new(
+        rule: &'a Rule<T>,
+        expression: Option<Box<dyn Expression<ExprType = T>>>,
+        span: Range<usize>,
+    ) -> Self {
+        Operation {
+            rule,
+            expression,
+            next_group: 0,
+            arguments: 1,
+            captured: 0,
+            separator: None,
+            operand_pending: false,
+            span,
+        }
+    }
+
+    /// Sets the number of arguments a function is called with
+    fn set_arguments(&mut self, num_inputs: usize) -> Result<(), Error> {
+        // functions that bind a variable keep their expression, which takes its arguments unevaluated
+        if self.rule.binds_variable() {
+            if num_inputs != 4 {
+                spanned_error!(
+                    ErrorCode::ArityMismatch,
+                    self.span.clone(),
+                    "Function '{}' takes an index variable, lower bound, upper bound and body but was given {} arguments",
+                    self.rule.binding().as_ref().map_or("", |(binding, _)| binding.label),
+                    num_inputs
+                );
+            }
+            return Ok(());
+        }
+        // functions defined by a program take a fixed number of arguments
+        if self.rule.user_function().is_some() {
+            if let Some(expression) = &self.expression {
+                if expression.num_inputs() != num_inputs {
+                    spanned_error!(
+                        ErrorCode::ArityMismatch,
+                        self.span.clone(),
+                        "Function '{}' takes {} arguments but was given {}",
+                        expression,
+                        expression.num_inputs(),
+                        num_inputs
+                    );
+                }
+            }
+            return Ok(());
+        }
+        // conditionals take their arguments unevaluated, so only the selected value is evaluated
+        if let Some(order) = self.rule.conditional() {
+            if num_inputs < 2 {
+                spanned_error!(
+                    ErrorCode::ArityMismatch,
+                    self.span.clone(),
+                    "Conditional takes at least a condition and a value but was given {} arguments",
+                    num_inputs
+                );
+            }
+            self.expression = Some(Box::new(
+                Conditional::new(order).with_num_arguments(num_inputs),
+            ));
+            return Ok(());
+        }
+        if let Some((binding, _)) = self.rule.binding() {
+            if !binding.arity.accepts(num_inputs) {
+                spanned_error!(
+                    ErrorCode::ArityMismatch,
+                    self.span.clone(),
+                    "Function '{}' takes {} arguments but was given {}",
+                    binding.label,
+                    binding.arity,
+                    num_inputs
+                );
+            }
+            self.expression = Some(Box::new(binding.clone().with_inputs(num_inputs)));
+        }
+        Ok(())
+    }
+
+    /// Whether the operation is a function that may be applied to a single value without brackets, e.g. sin x
+    fn accepts_single_value(&self) -> bool {
+        if self.rule.category() != Category::Functions || !self.rule.arguments().is_empty() {
+            return false;
+        }
+        match (self.rule.binding(), &self.expression) {
+            (Some((binding, _)), Some(_)) => binding.arity.accepts(1),
+            // functions defined by a program have a fixed number of inputs
+            (None, Some(expression)) => {
+                self.rule.user_function().is_some() && expression.num_inputs() == 1
+            }
+            (_, None) => false,
+        }
+    }
+
+    /// Whether the next argument group of a capturing function may be a single value without brackets,
+    /// e.g. the n in sum_(i=1)^n, given whether the prefix of the group has just been matched
+    fn accepts_bare_value(&self, group_prefixed: bool) -> bool {
+        self.rule.category() == Category::Functions
+            && !self.operand_pending
+            && self.pending_groups().first().is_some_and(|group| {
+                group.bare.unwrap_or(false) && (group.prefix.is_none() || group_prefixed)
+            })
+    }
+
+    fn pending_groups(&self) -> &'a [ArgumentDefinition] {
+        &self.rule.arguments()[self.next_group..]
+    }
+
+    /// Skips any optional argument groups that do not start with the given token, switching to the default binding.
+    /// Returns true if the function has no groups left and is complete.
+    fn skip_groups(&mut self, token: &Rule<T>) -> bool {
+        let pending = self.pending_groups();
+        if pending.is_empty() {
+            return false;
+        }
+        // groups with a prefix are started by the prefix, and skipped up to before parsing the token
+        let skipped = pending
+            .iter()
+            .take_while(|group| {
+                group.optional.unwrap_or(false)
+                    && group.prefix.is_none()
+                    && !(token.category() == Category::OpenBrackets
+                        && token.bracket_context() == group.context)
+                    && !(group.bare.unwrap_or(false) && is_value(token.category()))
+            })
+            .count();
+        self.skip(skipped);
+        self.pending_groups().is_empty()
+    }
+
+    /// Finds the pending argument group started by a prefix at the start of the string,
+    /// skipping any optional groups before it. Returns the length of the prefix.
+    fn start_prefixed_group(&mut self, equation_string: &str) -> Option<usize> {
+        for (index, group) in self.pending_groups().iter().enumerate() {
+            if let Some(prefix) = &group.prefix {
+                if equation_string.starts_with(prefix.as_str()) {
+                    self.skip(index);
+                    return Some(prefix.len());
+                }
+            }
+            if !group.optional.unwrap_or(false) {
+                break;
+            }
+        }
+        None
+    }
+
+    fn skip(&mut self, skipped: usize) {
+        if skipped > 0 {
+            self.next_group += skipped;
+            if let Some(default) = self.rule.default_binding() {
+                self.expression = Some(Box::new(default.clone()));
+            }
+        }
+    }
+
+    /// Records that an argument group has been closed. Returns true if the function has no groups left.
+    fn close_group(&mut self) -> bool {
+        if self.next_group < self.rule.arguments().len() {
+            self.next_group += 1;
+        }
+        self.pending_groups().is_empty()
+    }
+}
+
+/// A list in prefix notation whose closing bracket has not been reached yet, e.g. the (+ 1 2 of (+ 1 2)
+struct PrefixList<'r, 's, T: NumericType> {
+    // context of the opening bracket, and its byte offsets in the equation
+    context: i32,
+    open_span: Range<usize>,
+    // the operator or function applied to the other items of the list
+    head: &'r Rule<T>,
+    head_str: &'s str,
+    head_position: usize,
+    // number of items after the head so far
+    num_arguments: usize,
+}
+
+/// The variable or function a statement of a program assigns to
+enum Target<'a> {
+    Variable(Cow<'a, str>),
+    /// a function name and the names of its parameters
+    Function(Cow<'a, str>, Vec<Cow<'a, str>>),
+}
+
+impl Target<'_> {
+    fn label(&self) -> &str {
+        match self {
+            Target::Variable(label) | Target::Function(label, _) => label,
+        }
+    }
+}
+
+/// Functions defined by the statements of a program parsed so far
+struct Scope<T: NumericType> {
+    rules: Vec<Rule<T>>,
+    functions: Vec<Rc<UserFunction<T>>>,
+    case_sensitive: bool,
+}
+
+impl<T: NumericType<ExprType = T>> Scope<T> {
+    fn new(case_sensitive: bool) -> Scope<T> {
+        Scope {
+            rules: Vec::new(),
+            functions: Vec::new(),
+            case_sensitive,
+        }
+    }
+
+    fn defines(&self, label: &str) -> bool {
+        self.functions
+            .iter()
+            .any(|function| self.same_name(&function.label, label))
+    }
+
+    /// Whether two names refer to the same function or variable, given the case sensitivity of the syntax
+    fn same_name(&self, a: &str, b: &str) -> bool {
+        match self.case_sensitive {
+            true => a == b,
+            false => a.eq_ignore_ascii_case(b),
+        }
+    }
+
+    /// Adds a rule that matches the name of the function wherever the given categories come before it
+    fn define(&mut self, function: UserFunction<T>, follows: Vec<Category>) -> Result<(), Error> {
+        let pattern = match Regex::new(&format!(
+            r"^{}({})(.*)",
+            if self.case_sensitive { "" } else { "(?i)" },
+            regex::escape(&function.label)
+        )) {
+            Ok(re) => re,
+            Err(e) => return_error!(
+                ErrorType::SyntaxError,
+                "Function name '{}' is not valid: {}",
+                function.label,
+                e
+            ),
+        };
+        self.rules.push(
+            Rule::new_user_function_rule(pattern, follows, self.functions.len())
+                .with_literal_pattern(Some(function.label.clone())),
+        );
+        self.functions.push(Rc::new(function));
+        Ok(())
+    }
+}
+
+/// What is recorded while parsing an expression, besides the expressions themselves
+#[derive(Default)]
+struct ParseLog {
+    // tokens in the order they are written
+    tokens: Vec<Token>,
+    // errors parsing has carried on after, or None if parsing stops at the first error
+    errors: Option<Vec<Error>>,
+}
+
+impl ParseLog {
+    fn recovering() -> ParseLog {
+        ParseLog {
+            errors: Some(Vec::new()),
+            ..Default::default()
+        }
+    }
+
+    /// Records an error to carry on parsing after, or returns it if parsing stops at the first error
+    fn recover(&mut self, error: Error) -> Result<(), Error> {
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(error);
+                Ok(())
+            }
+            None => Err(error),
+        }
+    }
+
+    fn has_errors(&self) -> bool {
+        self.errors
+            .as_ref()
+            .is_some_and(|errors| !errors.is_empty())
+    }
+}
+
+/// Parser state that determines which rules are valid for the next token
+struct TokenContext<'a> {
+    last_token: Option<Category>,
+    // context of the innermost unclosed bracket
+    open_bracket: Option<i32>,
+    // argument groups still expected by a capturing function
+    pending_groups: &'a [ArgumentDefinition],
+    // whether the prefix of the first pending group has just been matched
+    group_prefixed: bool,
+    // the statement the token is in, to name the word around text no rule matches
+    statement: &'a str,
+}
+
 pub struct Parser<T: NumericType> {
     syntax_rules: Ruleset<T>,
+    notation: Notation,
 }
 
 impl<T: NumericType<ExprType = T>> Parser<T> {
@@ -32,7 +376,7 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
                 );
             }
         };
-        Self::from_json(json)
+        Ok(Self::from_json(json)?.with_notation(get_builtin_notation(&syntax)))
     }
 
     pub fn from_json(json: &str) -> Result<Parser<T>, Error> {
@@ -51,34 +395,1071 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
         // load and validate rules from definitions
         Ok(Parser::<T> {
             syntax_rules: Ruleset::create(definitions)?,
+            notation: Notation::Infix,
         })
     }
 
+    /// Sets where operators and functions are written relative to their arguments, which is infix by default.
+    /// In postfix and prefix notation, the `may_follow` fields of rules are not used.
+    pub fn with_notation(mut self, notation: Notation) -> Parser<T> {
+        self.notation = notation;
+        self
+    }
+
     pub fn parse(&self, equation_string: &str) -> Result<Equation<T>, Error> {
-        if equation_string.is_empty() {
-            syntax_error!("Equation string should not be empty");
+        let normalised = normalise_width(equation_string);
+        self.parse_equation(
+            &normalised,
+            &mut HashMap::new(),
+            &Scope::new(self.syntax_rules.case_sensitive()),
+            &mut ParseLog::default(),
+        )
+        .map_err(|error| restore_span(error, equation_string, &normalised))
+    }
+
+    /// Parses an equation, carrying on after each syntax error from the next token that may follow the tokens
+    /// before it, and closing any brackets left open, to find every error at once. The equation is kept if the rest
+    /// of it is still valid, e.g. 2 + # 3 gives an error for the # and the equation 2 + 3.
+    /// Only infix notation carries on after errors; other notations stop at the first.
+    pub fn parse_recovering(&self, equation_string: &str) -> ParseReport<T> {
+        let normalised = normalise_width(equation_string);
+        let mut log = ParseLog::recovering();
+        let scope = Scope::new(self.syntax_rules.case_sensitive());
+        let equation = self.parse_equation(&normalised, &mut HashMap::new(), &scope, &mut log);
+        let mut errors = log.errors.unwrap_or_default();
+        let equation = match equation {
+            Ok(equation) => Some(equation),
+            // an error with no position after others is caused by the parts of the equation left out
+            Err(error) if !errors.is_empty() && error.span.is_none() => None,
+            Err(error) => {
+                errors.push(error);
+                None
+            }
+        };
+        let mut errors: Vec<Error> = errors
+            .into_iter()
+            .map(|error| restore_span(error, equation_string, &normalised))
+            .collect();
+        errors.sort_by_key(|error| error.span.as_ref().map_or(usize::MAX, |span| span.start));
+        ParseReport {
+            equation,
+            errors,
+            warnings: self.warnings(&log.tokens, &scope, equation_string, &normalised),
+            repairs: Vec::new(),
+        }
+    }
+
+    /// Parses an equation that may still be being typed, e.g. to show its value as it is typed. Tokens at the end
+    /// that need something after them, such as operators, are left out and brackets left open are closed at the
+    /// end, so sin(x + is parsed as sin(x). Each change is given in the `repairs` of the report. Any other error
+    /// is reported as by `parse`, with no equation.
+    pub fn parse_lenient(&self, equation_string: &str) -> ParseReport<T> {
+        let normalised = normalise_width(equation_string);
+        let scope = Scope::new(self.syntax_rules.case_sensitive());
+        let mut text = normalised.trim_end();
+        // closing brackets added at the end, innermost first
+        let mut closing = String::new();
+        let mut repairs = Vec::new();
+        let error = loop {
+            let mut log = ParseLog::default();
+            let error = match self.parse_equation(
+                &format!("{}{}", text, closing),
+                &mut HashMap::new(),
+                &scope,
+                &mut log,
+            ) {
+                Ok(equation) => {
+                    return ParseReport {
+                        equation: Some(equation),
+                        errors: Vec::new(),
+                        warnings: self.warnings(&log.tokens, &scope, equation_string, &normalised),
+                        repairs,
+                    }
+                }
+                Err(error) => error,
+            };
+            let span = match error.span.clone().filter(|span| span.end <= text.len()) {
+                Some(span) => span,
+                None => break error,
+            };
+            let restored = restore_range(span.clone(), equation_string, &normalised);
+            match error.code {
+                Some(ErrorCode::UnexpectedEnd) => {
+                    repairs.push(Repair::DroppedToken {
+                        text: equation_string[restored.clone()].to_string(),
+                        span: restored,
+                    });
+                    text = text[..span.start].trim_end();
+                }
+                Some(ErrorCode::UnmatchedBracket) => {
+                    // a bracket that is still unclosed with a closing bracket added is not closed by it
+                    let already_closed = repairs.iter().any(|repair| {
+                        matches!(repair, Repair::ClosedBracket { opening, .. } if *opening == restored)
+                    });
+                    match self.closing_bracket(&text[span.start..], span.len()) {
+                        Some(close) if !already_closed => {
+                            closing.push(' ');
+                            closing.push_str(close);
+                            repairs.push(Repair::ClosedBracket {
+                                text: close.to_string(),
+                                opening: restored,
+                            });
+                        }
+                        _ => break error,
+                    }
+                }
+                _ => break error,
+            }
+        };
+        ParseReport {
+            equation: None,
+            errors: vec![restore_span(error, equation_string, &normalised)],
+            warnings: Vec::new(),
+            repairs,
+        }
+    }
+
+    /// Warnings about the tokens of an equation, with spans in the equation as given
+    fn warnings(
+        &self,
+        tokens: &[Token],
+        scope: &Scope<T>,
+        equation_string: &str,
+        normalised: &str,
+    ) -> Vec<Error> {
+        tokens
+            .iter()
+            .filter_map(|token| self.misspelt_function(token, scope))
+            .map(|warning| restore_span(warning, equation_string, normalised))
+            .collect()
+    }
+
+    /// Splits an equation into the tokens matched by each rule, in the order they are written, e.g. for
+    /// highlighting or completion in an editor. The spans of each token are offsets in `equation_string`
+    /// as given, before full-width characters are replaced, so a token can be found again by slicing it.
+    pub fn tokenize(&self, equation_string: &str) -> Result<Vec<Token>, Error> {
+        let normalised = normalise_width(equation_string);
+        let mut log = ParseLog::default();
+        self.parse_expressions(
+            &normalised,
+            &mut HashMap::new(),
+            &Scope::new(self.syntax_rules.case_sensitive()),
+            &mut log,
+        )
+        .map_err(|error| restore_span(error, equation_string, &normalised))?;
+
+        // normalising replaces each character with one other, so tokens keep their character offsets
+        let mut char_offsets: Vec<usize> = equation_string.char_indices().map(|(i, _)| i).collect();
+        char_offsets.push(equation_string.len());
+        let mut tokens = log.tokens;
+        for token in tokens.iter_mut() {
+            let start = normalised[..token.span.start].chars().count();
+            let end = start + token.text.chars().count();
+            token.char_span = start..end;
+            token.span = char_offsets[start]..char_offsets[end];
+            token.text = equation_string[token.span.clone()].to_string();
+        }
+        Ok(tokens)
+    }
+
+    /// Parses a list of statements divided by StatementSeparators rules, each of which may assign its value
+    /// to a variable with an Assignments rule, e.g. r = sqrt(x^2 + y^2); theta = atan(y / x); r * cos(theta),
+    /// or define a function that later statements may call, e.g. f(t) = t^2 + 1; f(x) + f(2x).
+    /// Variables that are used before they are assigned are inputs of the program.
+    pub fn parse_program(&self, program_string: &str) -> Result<Program<T>, Error> {
+        let normalised = normalise_width(program_string);
+        self.parse_statements(&normalised)
+            .map_err(|error| restore_span(error, program_string, &normalised))
+    }
+
+    /// Parses the statements of a program, after full-width characters are replaced
+    fn parse_statements(&self, program_string: &str) -> Result<Program<T>, Error> {
+        // every statement shares the variable of the same name
+        let mut variables: HashMap<String, Rc<RefCell<T>>> = HashMap::new();
+        let mut inputs: HashMap<String, Rc<RefCell<T>>> = HashMap::new();
+        let mut assigned: Vec<String> = Vec::new();
+        let mut scope = Scope::new(self.syntax_rules.case_sensitive());
+        let mut statements = Vec::new();
+
+        for statement_string in self.split_statements(program_string) {
+            let statement_start = subslice_start(program_string, statement_string);
+            let (target, equation_string) = self
+                .split_assignment(statement_string)
+                .map_err(|error| error.offset_span(statement_start))?;
+            // errors about the target of an assignment cover the target and the assignment
+            let equation_start = subslice_start(program_string, equation_string);
+            let target_span = statement_start
+                ..statement_start
+                    + statement_string[..equation_start - statement_start]
+                        .trim_end()
+                        .len();
+            if let Some(label) = target.as_ref().map(Target::label) {
+                if scope.defines(label) {
+                    spanned_error!(
+                        ErrorCode::InvalidAssignment,
+                        target_span,
+                        "Function '{}' is already defined",
+                        label
+                    );
+                }
+            }
+            let target = match target {
+                Some(Target::Function(label, parameters)) => {
+                    if variables.contains_key(label.as_ref()) {
+                        spanned_error!(
+                            ErrorCode::InvalidAssignment,
+                            target_span,
+                            "Function '{}' has the same name as a variable used before it",
+                            label
+                        );
+                    }
+                    // a parameter named like a function would be read as the function in the body
+                    if let Some(parameter) = parameters.iter().find(|parameter| {
+                        scope.defines(parameter) || scope.same_name(parameter, &label)
+                    }) {
+                        spanned_error!(
+                            ErrorCode::InvalidAssignment,
+                            target_span,
+                            "Parameter '{}' of function '{}' has the same name as a function",
+                            parameter,
+                            label
+                        );
+                    }
+                    let definition = self
+                        .parse_function(&label, parameters, equation_string, &variables, &scope)
+                        .map_err(|error| error.offset_span(equation_start))?;
+                    // variables used by the body are the same as those of the program
+                    let mut used = HashMap::new();
+                    definition.collect_variables(&mut used);
+                    for (label, cell) in used {
+                        variables
+                            .entry(label.clone())
+                            .or_insert_with(|| cell.clone());
+                        if !assigned.contains(&label) {
+                            inputs.insert(label, cell);
+                        }
+                    }
+                    let follows = match self.match_variable(&label) {
+                        Some((rule, _, _)) => rule.follows().to_vec(),
+                        None => Vec::new(),
+                    };
+                    scope.define(definition, follows)?;
+                    continue;
+                }
+                target => target,
+            };
+            let equation = self
+                .parse_equation(
+                    equation_string,
+                    &mut variables,
+                    &scope,
+                    &mut ParseLog::default(),
+                )
+                .map_err(|error| error.offset_span(equation_start))?;
+            for label in equation.variables() {
+                if !assigned.contains(label) {
+                    inputs.insert(label.clone(), variables[label].clone());
+                }
+            }
+            let target = match target {
+                Some(target) => {
+                    let label = target.label();
+                    if inputs.contains_key(label) {
+                        spanned_error!(
+                            ErrorCode::InvalidAssignment,
+                            target_span,
+                            "Variable '{}' is assigned after it is used as an input",
+                            label
+                        );
+                    }
+                    assigned.push(label.to_string());
+                    let cell = variables
+                        .entry(label.to_string())
+                        .or_insert_with(|| Rc::new(RefCell::new(T::zero())));
+                    Some((label.to_string(), cell.clone()))
+                }
+                None => None,
+            };
+            statements.push(Statement { target, equation });
+        }
+
+        if statements.is_empty() {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                "Program should contain at least one statement".to_string(),
+            )
+            .with_code(ErrorCode::EmptyExpression));
+        }
+        Ok(Program::new(statements, inputs))
+    }
+
+    /// Parses the body of a function defined by a program, in which the parameters hide any variables of the same name
+    fn parse_function(
+        &self,
+        label: &str,
+        parameters: Vec<Cow<str>>,
+        body_string: &str,
+        variables: &HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+    ) -> Result<UserFunction<T>, Error> {
+        let mut body_variables = variables.clone();
+        let mut parameter_cells: Vec<(String, Rc<RefCell<T>>)> = Vec::new();
+        for parameter in parameters {
+            if parameter_cells.iter().any(|(name, _)| *name == parameter) {
+                syntax_error!(
+                    "Function '{}' has more than one parameter named '{}'",
+                    label,
+                    parameter
+                );
+            }
+            let cell = Rc::new(RefCell::new(T::zero()));
+            body_variables.insert(parameter.to_string(), cell.clone());
+            parameter_cells.push((parameter.to_string(), cell));
+        }
+        let mut log = ParseLog::default();
+        let body = self.parse_expressions(body_string, &mut body_variables, scope, &mut log)?;
+        // the function is not yet defined in its own body, so its name would be read as a variable
+        if let Some(token) = log.tokens.iter().find(|token| {
+            token.category == Category::Variables && scope.same_name(&token.text, label)
+        }) {
+            spanned_error!(
+                ErrorCode::InvalidAssignment,
+                token.span.clone(),
+                "Function '{}' may not use its own name in its body",
+                label
+            );
+        }
+        // run through the body to check for any syntax errors that were not caught by the rules
+        if let Err(e) = evaluate_expressions(&body) {
+            if matches!(e.error_type, ErrorType::SyntaxError) {
+                return Err(leftover_values(e, body_string));
+            }
+        }
+        Ok(UserFunction::new(label, parameter_cells, body))
+    }
+
+    /// Splits a program at each match of a StatementSeparators rule, leaving out empty statements
+    fn split_statements<'b>(&self, program_string: &'b str) -> Vec<&'b str> {
+        let separators: Vec<&Rule<T>> = self
+            .syntax_rules
+            .iter()
+            .filter(|rule| rule.category() == Category::StatementSeparators)
+            .collect();
+        let mut statements = Vec::new();
+        let mut start = 0;
+        let mut index = 0;
+        while index < program_string.len() {
+            let separator = separators
+                .iter()
+                .filter_map(|rule| rule.get_match(&program_string[index..]))
+                .map(|(matched, _)| matched.len())
+                .filter(|&length| length > 0)
+                .max();
+            match separator {
+                Some(length) => {
+                    statements.push(&program_string[start..index]);
+                    index += length;
+                    start = index;
+                }
+                None => {
+                    index += program_string[index..]
+                        .chars()
+                        .next()
+                        .map_or(1, char::len_utf8);
+                }
+            }
+        }
+        statements.push(&program_string[start..]);
+        statements
+            .into_iter()
+            .map(str::trim)
+            .filter(|statement| !statement.is_empty())
+            .collect()
+    }
+
+    /// Splits a statement into the variable or function it assigns, if any, and the equation giving its value
+    fn split_assignment<'b>(
+        &self,
+        statement: &'b str,
+    ) -> Result<(Option<Target<'b>>, &'b str), Error> {
+        let (rule, target, remaining) = match self.match_variable(statement) {
+            Some((rule, target, remaining)) => (rule, target, remaining.trim_start()),
+            None => return Ok((None, statement)),
+        };
+        // a list of parameters after the name defines a function, e.g. f(t) = t^2 + 1
+        let (parameters, remaining) = match self.match_parameters(remaining) {
+            Some((parameters, remaining)) => (Some(parameters), remaining.trim_start()),
+            None => (None, remaining),
+        };
+        let assignment = self
+            .syntax_rules
+            .iter()
+            .filter(|rule| rule.category() == Category::Assignments)
+            .filter_map(|rule| rule.get_match(remaining))
+            .map(|(matched, _)| matched.len())
+            .max();
+        let assignment = match assignment {
+            Some(length) => length,
+            None => return Ok((None, statement)),
+        };
+        // an operator that matches more of the statement takes precedence, e.g. == over =
+        let longer_token = self
+            .syntax_rules
+            .iter()
+            .filter(|rule| rule.is_token())
+            .filter_map(|rule| rule.get_match(remaining))
+            .any(|(matched, _)| matched.len() > assignment);
+        if longer_token {
+            return Ok((None, statement));
+        }
+        // function and constant names are reserved, so may not be assigned
+        let reserved = self
+            .syntax_rules
+            .iter()
+            .filter(|rule| {
+                matches!(
+                    rule.category(),
+                    Category::Functions | Category::Constants | Category::PrefixOperators
+                )
+            })
+            .filter_map(|rule| rule.get_match(statement))
+            .any(|(matched, _)| matched.len() >= target.len());
+        if reserved {
+            spanned_error!(
+                ErrorCode::InvalidAssignment,
+                0..target.len(),
+                "'{}' is a reserved name and may not be assigned",
+                target
+            );
+        }
+        let target = rule.variable_mode().label(target);
+        let target = match parameters {
+            Some(parameters) => Target::Function(target, parameters),
+            None => Target::Variable(target),
+        };
+        Ok((Some(target), remaining[assignment..].trim_start()))
+    }
+
+    /// Matches a bracketed list of variables at the start of the string, e.g. the (a, b) of f(a, b) = a * b
+    fn match_parameters<'b>(
+        &self,
+        equation_string: &'b str,
+    ) -> Option<(Vec<Cow<'b, str>>, &'b str)> {
+        let (open, remaining) = self
+            .syntax_rules
+            .iter()
+            .filter(|rule| {
+                rule.category() == Category::OpenBrackets
+                    && rule.binding().is_none()
+                    && rule.conditional().is_none()
+            })
+            .find_map(|rule| Some((rule, rule.get_match(equation_string)?.1)))?;
+        let close = |equation_string: &'b str| {
+            self.syntax_rules
+                .iter()
+                .filter(|rule| {
+                    rule.category() == Category::CloseBrackets
+                        && rule.bracket_context() == open.bracket_context()
+                })
+                .find_map(|rule| Some(rule.get_match(equation_string)?.1))
+        };
+
+        let mut parameters = Vec::new();
+        let mut remaining = remaining.trim_start();
+        loop {
+            let (rule, parameter, after) = self.match_variable(remaining)?;
+            parameters.push(rule.variable_mode().label(parameter));
+            remaining = after.trim_start();
+            if let Some(remaining) = close(remaining) {
+                return Some((parameters, remaining));
+            }
+            remaining = self
+                .syntax_rules
+                .iter()
+                .filter(|rule| rule.category() == Category::Separators)
+                .find_map(|rule| Some(rule.get_match(remaining)?.1))?
+                .trim_start();
+        }
+    }
+
+    /// Parses an equation whose variables use the cell of the same name in `variables` if there is one
+    fn parse_equation(
+        &self,
+        equation_string: &str,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+        log: &mut ParseLog,
+    ) -> Result<Equation<T>, Error> {
+        let expressions = self.parse_expressions(equation_string, variables, scope, log)?;
+        // variables bound by an expression such as a summation are not inputs of the equation
+        let mut variables = HashMap::new();
+        for expression in &expressions {
+            expression.collect_variables(&mut variables);
+        }
+
+        let equation = Equation::new(expressions, variables);
+
+        // run through equation to check for any syntax errors that were not caught by the rules
+        match equation.evaluate() {
+            Ok(_) => Ok(equation),
+            Err(e) => match e.error_type {
+                // after other errors, the values are left over because of the parts left out
+                ErrorType::SyntaxError if log.has_errors() => Err(e),
+                ErrorType::SyntaxError => Err(leftover_values(e, equation_string)),
+                _ => Ok(equation),
+            },
+        }
+    }
+
+    /// Parses an expression into a list of expressions to be evaluated left to right, recording each token in the log
+    fn parse_expressions(
+        &self,
+        equation_string: &str,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+        log: &mut ParseLog,
+    ) -> Result<Expressions<T>, Error> {
+        if equation_string.trim().is_empty() {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                "Equation string should not be empty".to_string(),
+            )
+            .with_code(ErrorCode::EmptyExpression));
+        }
+        match self.notation {
+            Notation::Infix => self.parse_infix_expressions(equation_string, variables, scope, log),
+            Notation::Postfix => {
+                self.parse_postfix_expressions(equation_string, variables, scope, log)
+            }
+            Notation::Prefix => {
+                self.parse_prefix_expressions(equation_string, variables, scope, log)
+            }
+        }
+    }
+
+    /// Parses an expression in postfix notation, e.g. 3 4 + 2 *, where each operator and function follows its arguments
+    fn parse_postfix_expressions(
+        &self,
+        equation_string: &str,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+        log: &mut ParseLog,
+    ) -> Result<Expressions<T>, Error> {
+        let mut expressions: Expressions<T> = Vec::new();
+        // number of values left by the expressions so far
+        let mut depth = 0;
+        let mut remainder = equation_string.trim();
+        while !remainder.is_empty() {
+            let position = offset(equation_string, remainder);
+            let (rule, matched_str, remaining_str) =
+                self.match_any_token(equation_string, position, scope)?;
+            log.tokens.push(Token::new(
+                rule.category(),
+                matched_str,
+                binding_label(rule),
+                position,
+            ));
+            remainder = remaining_str.trim();
+            let span = position..position + matched_str.len();
+            let expression =
+                self.create_expression(rule, matched_str, variables, scope, position)?;
+            let expression = match rule.category() {
+                Category::Fluff => continue,
+                Category::Literals | Category::Constants | Category::Variables => {
+                    expression.unwrap()
+                }
+                Category::Operators | Category::PrefixOperators | Category::PostfixOperators => {
+                    expression.unwrap()
+                }
+                Category::Functions if !rule.has_differential() => {
+                    // functions taking a varying number of arguments take as few as they can, but at least two
+                    let num_inputs = match (&expression, rule.binding()) {
+                        _ if rule.binds_variable() => 4,
+                        _ if rule.conditional().is_some() => 3,
+                        (Some(expression), _) if rule.user_function().is_some() => {
+                            expression.num_inputs()
+                        }
+                        (_, Some((binding, _))) => match binding.arity {
+                            Arity::Exactly(n) => n,
+                            Arity::AtLeast(n) | Arity::Between(n, _) => n.max(2),
+                        },
+                        _ => 0,
+                    };
+                    let mut operation = Operation::new(rule, expression, span.clone());
+                    operation.set_arguments(num_inputs)?;
+                    operation.expression.unwrap()
+                }
+                _ => spanned_error!(
+                    ErrorCode::UnexpectedToken,
+                    span,
+                    "{} rule '{}' cannot be used in postfix notation",
+                    rule.category(),
+                    matched_str
+                ),
+            };
+            let num_arguments = expression.num_inputs() + expression.num_captured();
+            if num_arguments > depth {
+                spanned_error!(
+                    ErrorCode::ArityMismatch,
+                    span,
+                    "'{}' takes {} arguments but is given {}",
+                    matched_str,
+                    num_arguments,
+                    depth
+                );
+            }
+            depth = depth + 1 - num_arguments;
+            expressions.push(expression);
+        }
+        if depth != 1 {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                format!(
+                    "Expression leaves {} values instead of a single value",
+                    depth
+                ),
+            )
+            .with_code(ErrorCode::ArityMismatch)
+            .with_span(0..equation_string.trim_end().len())
+            .with_help("each operator and function takes the values before it, so all but one value must be used as arguments"));
+        }
+        capture_arguments(expressions)
+    }
+
+    /// Parses an expression in prefix notation, e.g. (* (+ 3 4) 2), where the first item of each bracketed list is
+    /// applied to the others. Unclosed lists are kept on a stack, so deeply nested lists are parsed without recursion.
+    fn parse_prefix_expressions(
+        &self,
+        equation_string: &str,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+        log: &mut ParseLog,
+    ) -> Result<Expressions<T>, Error> {
+        let mut expressions: Expressions<T> = Vec::new();
+        let mut lists: Vec<PrefixList<T>> = Vec::new();
+        let mut remainder = equation_string.trim();
+        loop {
+            let position = offset(equation_string, remainder);
+            let close = match lists.last() {
+                Some(list) if remainder.is_empty() => {
+                    return Err(unclosed_bracket(list.open_span.clone()))
+                }
+                Some(list) => self.match_close_bracket(remainder, list.context),
+                None => None,
+            };
+            if let Some((close, rest)) = close {
+                log.tokens
+                    .push(Token::new(Category::CloseBrackets, close, None, position));
+                remainder = rest.trim();
+                let list = lists.pop().unwrap();
+                self.apply_prefix_list(list, &mut expressions, variables, scope)?;
+            } else {
+                let (rule, matched_str, rest) =
+                    self.match_any_token(equation_string, position, scope)?;
+                log.tokens.push(Token::new(
+                    rule.category(),
+                    matched_str,
+                    binding_label(rule),
+                    position,
+                ));
+                remainder = rest.trim();
+                match rule.category() {
+                    Category::Fluff => continue,
+                    Category::Literals | Category::Constants | Category::Variables => {
+                        let expression =
+                            self.create_expression(rule, matched_str, variables, scope, position)?;
+                        expressions.push(expression.unwrap());
+                    }
+                    Category::OpenBrackets
+                        if rule.binding().is_none() && rule.conditional().is_none() =>
+                    {
+                        let open_span = position..position + matched_str.len();
+                        let head_position = offset(equation_string, remainder);
+                        if remainder.is_empty() {
+                            return Err(unclosed_bracket(open_span));
+                        }
+                        if let Some((close, _)) =
+                            self.match_close_bracket(remainder, rule.bracket_context())
+                        {
+                            spanned_error!(
+                                ErrorCode::EmptyExpression,
+                                position..head_position + close.len(),
+                                "List is empty"
+                            );
+                        }
+                        // the first item of a list is the operator or function applied to the others
+                        let (head, head_str, rest) =
+                            self.match_any_token(equation_string, head_position, scope)?;
+                        log.tokens.push(Token::new(
+                            head.category(),
+                            head_str,
+                            binding_label(head),
+                            head_position,
+                        ));
+                        remainder = rest.trim();
+                        lists.push(PrefixList {
+                            context: rule.bracket_context(),
+                            open_span,
+                            head,
+                            head_str,
+                            head_position,
+                            num_arguments: 0,
+                        });
+                        continue;
+                    }
+                    _ => spanned_error!(
+                        ErrorCode::UnexpectedToken,
+                        position..position + matched_str.len(),
+                        "{} rule '{}' must be the first item of a list",
+                        rule.category(),
+                        matched_str
+                    ),
+                }
+            }
+
+            // a value or list is complete, so it is the next argument of the list it is in, if any
+            let list = match lists.last_mut() {
+                Some(list) => list,
+                None => break,
+            };
+            list.num_arguments += 1;
+            // an operator is applied to each argument after the first in turn, e.g. (- 10 2 3) is (10 - 2) - 3
+            if list.head.category() == Category::Operators && list.num_arguments >= 2 {
+                expressions.extend(self.create_expression(
+                    list.head,
+                    list.head_str,
+                    variables,
+                    scope,
+                    list.head_position,
+                )?);
+            }
+        }
+
+        if !remainder.is_empty() {
+            let position = offset(equation_string, remainder);
+            spanned_error!(
+                ErrorCode::UnexpectedToken,
+                position..position + remainder.len(),
+                "Unexpected '{}' after the end of the expression",
+                remainder
+            );
+        }
+        capture_arguments(expressions)
+    }
+
+    /// Outputs the operator or function at the head of a prefix notation list once all of its arguments are output
+    fn apply_prefix_list(
+        &self,
+        list: PrefixList<T>,
+        expressions: &mut Expressions<T>,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+    ) -> Result<(), Error> {
+        let PrefixList {
+            head,
+            head_str,
+            head_position,
+            num_arguments,
+            ..
+        } = list;
+        let head_span = head_position..head_position + head_str.len();
+        match head.category() {
+            Category::Operators if num_arguments >= 2 => {}
+            // an operator given a single argument is the prefix operator of the same name, e.g. (- x)
+            Category::Operators | Category::PrefixOperators | Category::PostfixOperators
+                if num_arguments == 1 =>
+            {
+                let unary = match head.category() {
+                    Category::Operators => self.syntax_rules.iter().find(|rule| {
+                        rule.category() == Category::PrefixOperators
+                            && rule
+                                .get_match(head_str)
+                                .is_some_and(|(matched, _)| matched == head_str)
+                    }),
+                    _ => Some(head),
+                };
+                match unary {
+                    Some(rule) => expressions.extend(self.create_expression(
+                        rule,
+                        head_str,
+                        variables,
+                        scope,
+                        head_position,
+                    )?),
+                    None => spanned_error!(
+                        ErrorCode::ArityMismatch,
+                        head_span,
+                        "Operator '{}' requires at least 2 arguments",
+                        head_str
+                    ),
+                }
+            }
+            Category::Functions if !head.has_differential() => {
+                let expression =
+                    self.create_expression(head, head_str, variables, scope, head_position)?;
+                let mut operation = Operation::new(head, expression, head_span);
+                operation.set_arguments(num_arguments)?;
+                expressions.extend(operation.expression);
+            }
+            _ => spanned_error!(
+                ErrorCode::ArityMismatch,
+                head_span,
+                "{} rule '{}' cannot be applied to {} arguments",
+                head.category(),
+                head_str,
+                num_arguments
+            ),
+        }
+        Ok(())
+    }
+
+    /// Matches the longest token at the given byte offset of the equation, for notations where which tokens
+    /// are valid does not depend on the tokens before them
+    fn match_any_token<'r, 's>(
+        &'r self,
+        equation_string: &'s str,
+        position: usize,
+        scope: &'r Scope<T>,
+    ) -> Result<(&'r Rule<T>, &'s str, &'s str), Error> {
+        let remainder = &equation_string.trim_end()[position..];
+        match self
+            .token_rules(scope)
+            .filter_map(|rule| {
+                let (matched, remaining) = rule.get_match(remainder)?;
+                Some((rule, matched, remaining))
+            })
+            .filter(|(_, matched, _)| !matched.is_empty())
+            .max_by_key(|(rule, matched, _)| (matched.len(), rule.priority()))
+        {
+            Some(token) => Ok(token),
+            None => Err(self.unknown_token(equation_string, position, scope)),
         }
+    }
 
-        let mut variables: HashMap<String, Rc<RefCell<T>>> = HashMap::new();
+    /// Parses an expression in infix notation with the shunting-yard algorithm
+    fn parse_infix_expressions(
+        &self,
+        equation_string: &str,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+        log: &mut ParseLog,
+    ) -> Result<Expressions<T>, Error> {
         let mut expressions: Vec<Box<dyn Expression<ExprType = T>>> = Vec::new();
-        let mut operator_stack: Vec<(Box<Rule<T>>, Option<Box<dyn Expression<ExprType = T>>>)> =
-            Vec::new();
+        let mut operator_stack: Vec<Operation<T>> = Vec::new();
 
         let mut remainder = equation_string.trim().to_string();
-        let mut position = equation_string.rfind(&remainder).unwrap_or(0);
         let mut last_token: Option<Category> = None;
+        // the last token before the latest implicit operator, in case an error follows the operator
+        let mut token_before_implicit: Option<Category> = None;
         let mut bracket_context = Vec::new();
+        // whether the last token was a range of cells
+        let mut range_pending = false;
         while !remainder.is_empty() {
-            let (rule, matched_str, remaining_str) =
-                self.match_next_token(&remainder, &last_token, position)?;
+            let mut position = offset(equation_string, &remainder);
+            // some argument groups are split into several arguments by their own separator, e.g. the = in \sum_{i=1}
+            let group_bracket = operator_stack
+                .iter_mut()
+                .rev()
+                .find(|operation| operation.rule.category() == Category::OpenBrackets);
+            if let Some(separator) = group_bracket
+                .as_ref()
+                .and_then(|bracket| bracket.separator)
+                .filter(|separator| remainder.starts_with(separator))
+            {
+                group_bracket.unwrap().arguments += 1;
+                while let Some(top_of_stack) = operator_stack.last() {
+                    if Category::OpenBrackets == top_of_stack.rule.category() {
+                        break;
+                    }
+                    if let Some(expr) = operator_stack.pop().unwrap().expression {
+                        expressions.push(expr);
+                    }
+                }
+                log.tokens
+                    .push(Token::new(Category::Separators, separator, None, position));
+                remainder = remainder[separator.len()..].trim().to_string();
+                last_token = Some(Category::Separators);
+                continue;
+            }
+
+            // a differential such as the dx in \int_{0}^{1} x^2 \, dx ends the body of the innermost integral
+            if let Some(index) = operator_stack
+                .iter()
+                .rposition(|operation| {
+                    operation.rule.category() == Category::OpenBrackets
+                        || (operation.operand_pending && operation.rule.has_differential())
+                })
+                .filter(|&index| operator_stack[index].operand_pending)
+            {
+                let body_complete = matches!(
+                    last_token,
+                    Some(
+                        Category::Literals
+                            | Category::Constants
+                            | Category::Variables
+                            | Category::CloseBrackets
+                            | Category::PostfixOperators
+                            | Category::Fluff
+                    )
+                );
+                if let Some((differential, (rule, matched_str, remaining_str))) = operator_stack
+                    [index]
+                    .rule
+                    .match_differential(&remainder)
+                    .filter(|_| body_complete)
+                    .and_then(|rest| Some((rest, self.match_variable(rest)?)))
+                {
+                    let differential = remainder[..remainder.len() - differential.len()].trim_end();
+                    log.tokens
+                        .push(Token::new(Category::Fluff, differential, None, position));
+                    log.tokens.push(Token::new(
+                        rule.category(),
+                        matched_str,
+                        None,
+                        offset(equation_string, remaining_str) - matched_str.len(),
+                    ));
+                    while operator_stack.len() > index + 1 {
+                        if let Some(expr) = operator_stack.pop().unwrap().expression {
+                            expressions.push(expr);
+                        }
+                    }
+                    // the variable of integration comes after the body and before the integral
+                    let variable = self.create_expression(
+                        rule,
+                        matched_str,
+                        variables,
+                        scope,
+                        offset(equation_string, remaining_str) - matched_str.len(),
+                    )?;
+                    expressions.extend(variable);
+                    if let Some(expr) = operator_stack.pop().unwrap().expression {
+                        expressions.push(expr);
+                    }
+                    remainder = remaining_str.trim().to_string();
+                    last_token = Some(Category::Variables);
+                    continue;
+                }
+            }
+
+            // a prefix such as the ^ in \sum_{i=1}^{n} starts the next argument group
+            let group_prefixed = match operator_stack
+                .last_mut()
+                .and_then(|operation| operation.start_prefixed_group(&remainder))
+            {
+                Some(length) => {
+                    log.tokens.push(Token::new(
+                        Category::Separators,
+                        &remainder[..length],
+                        None,
+                        position,
+                    ));
+                    remainder = remainder[length..].trim().to_string();
+                    position = offset(equation_string, &remainder);
+                    true
+                }
+                None => false,
+            };
+            // argument groups that a capturing function at the top of the stack is waiting for
+            let pending_groups = match operator_stack.last() {
+                Some(operation) => operation.pending_groups(),
+                None => &[],
+            };
+            let context = TokenContext {
+                last_token,
+                open_bracket: bracket_context.last().copied(),
+                pending_groups,
+                group_prefixed,
+                statement: equation_string,
+            };
+            let (rule, matched_str, remaining_str) = match self
+                .match_next_token(&remainder, &context, position, scope)
+            {
+                Ok(token) => token,
+                Err(error) => {
+                    log.recover(error)?;
+                    // an implicit operator is only implied by the text after it, so is skipped along with it
+                    if last_token == Some(Category::ImplicitOperators) {
+                        operator_stack.pop();
+                        log.tokens.pop();
+                        last_token = token_before_implicit;
+                    }
+                    let context = TokenContext {
+                        last_token,
+                        pending_groups: operator_stack
+                            .last()
+                            .map_or(&[], |operation| operation.pending_groups()),
+                        ..context
+                    };
+                    remainder = self.resynchronise(&remainder, position, &context, scope, log)?;
+                    // text with an error at the end of a bracket, e.g. the # of (1 + #), leaves the bracket to close
+                    if let Some((close, rest)) = context
+                        .open_bracket
+                        .and_then(|open| self.match_close_bracket(&remainder, open))
+                    {
+                        Self::leave_out_dangling(&mut operator_stack, last_token);
+                        log.tokens.push(Token::new(
+                            Category::CloseBrackets,
+                            close,
+                            None,
+                            offset(equation_string, &remainder),
+                        ));
+                        bracket_context.pop();
+                        last_token = Some(Category::CloseBrackets);
+                        if Self::close_bracket(&mut operator_stack, &mut expressions, log)? {
+                            last_token = Some(Category::PrefixOperators);
+                        }
+                        remainder = rest.trim().to_string();
+                    }
+                    continue;
+                }
+            };
+            let span = position..position + matched_str.len();
+            // a range is a whole argument, so only the end of the argument may follow it
+            if range_pending {
+                if !matches!(
+                    rule.category(),
+                    Category::Separators | Category::CloseBrackets | Category::Fluff
+                ) {
+                    log.recover(
+                        Error::new(
+                            ErrorType::SyntaxError,
+                            format!(
+                                "Range must be a whole argument, but is followed by '{}'",
+                                matched_str
+                            ),
+                        )
+                        .with_code(ErrorCode::UnexpectedToken)
+                        .with_span(span.clone()),
+                    )?;
+                }
+                range_pending = rule.category() == Category::Fluff;
+            }
+            log.tokens.push(Token::new(
+                rule.category(),
+                &matched_str,
+                binding_label(rule),
+                position,
+            ));
             remainder = remaining_str.trim().to_string();
             if remainder.is_empty() && !rule.allowed_at_end() {
-                syntax_error!(
-                    "{} may not appear at the end of an expression",
-                    rule.category()
-                )
+                log.recover(
+                    Error::new(
+                        ErrorType::SyntaxError,
+                        format!(
+                            "{} may not appear at the end of an expression",
+                            rule.category()
+                        ),
+                    )
+                    .with_code(ErrorCode::UnexpectedEnd)
+                    .with_span(span)
+                    .with_help(format!(
+                        "add what '{}' applies to after it, or remove it",
+                        matched_str
+                    )),
+                )?;
+                // the token is left out, as nothing it applies to follows
+                break;
             }
-            last_token = Some(rule.category());
             /*
              * if the token is:
              *   - a number:
@@ -108,62 +1489,187 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
              *        if there is a function token at the top of the operator stack, then:
              *            pop the function from the operator stack into the output queue
              */
-            let expression = self.create_expression(&rule, &matched_str, &mut variables)?;
+            let expression =
+                match self.create_expression(rule, &matched_str, variables, scope, position) {
+                    Ok(expression) => expression,
+                    Err(error) => {
+                        // the token is left out, so the token before it is still the last
+                        log.recover(error)?;
+                        continue;
+                    }
+                };
+            if rule.category() == Category::ImplicitOperators {
+                token_before_implicit = last_token;
+            }
+            last_token = Some(rule.category());
+
+            // a capturing function only accepts its argument groups, skipping any optional groups that were left out
+            if rule.category() != Category::Fluff {
+                if let Some(operation) = operator_stack.last_mut() {
+                    if operation.skip_groups(rule) {
+                        if let Some(expr) = operator_stack.pop().unwrap().expression {
+                            expressions.push(expr);
+                        }
+                    }
+                }
+            }
 
             match rule.category() {
-                Category::Fluff => {}
+                // statement rules are never matched as tokens
+                Category::Fluff | Category::Assignments | Category::StatementSeparators => {}
+                // a range such as A1:A3 supplies the enclosing function an argument for each cell
+                Category::Variables if rule.reference() == Some(Reference::CellRange) => {
+                    let labels = match rule.reference().unwrap().labels(&matched_str) {
+                        Some(labels) => labels,
+                        None => {
+                            log.recover(
+                                Error::new(
+                                    ErrorType::SyntaxError,
+                                    format!("'{}' is not a valid range", matched_str),
+                                )
+                                .with_code(ErrorCode::InvalidReference)
+                                .with_span(span),
+                            )?;
+                            continue;
+                        }
+                    };
+                    match operator_stack.last_mut() {
+                        Some(bracket) if bracket.rule.category() == Category::OpenBrackets => {
+                            bracket.arguments += labels.len() - 1
+                        }
+                        _ => {
+                            log.recover(
+                                Error::new(
+                                    ErrorType::SyntaxError,
+                                    format!(
+                                        "Range '{}' must be an argument of a function",
+                                        matched_str
+                                    ),
+                                )
+                                .with_code(ErrorCode::InvalidReference)
+                                .with_span(span),
+                            )?;
+                            continue;
+                        }
+                    }
+                    for label in labels {
+                        expressions.push(Self::create_variable(&label, variables));
+                    }
+                    range_pending = true;
+                }
                 Category::Literals | Category::Constants | Category::Variables => {
                     expressions.push(expression.unwrap());
-                    // if value directly follows a single-argument function, push function to output
-                    if let Some((rule, expr)) = operator_stack.last() {
-                        if rule.category() == Category::Functions
-                            && rule.binding().is_some()
-                            && rule.binding().as_ref().unwrap().0.num_inputs == 1
-                            && expr.is_some()
-                        {
-                            expressions.push(operator_stack.pop().unwrap().1.unwrap());
+                    if let Some(operation) = operator_stack.last_mut() {
+                        if operation.accepts_bare_value(group_prefixed) {
+                            // the value is a whole argument group, e.g. the n in sum_(i=1)^n
+                            match Self::close_argument_group(
+                                &mut operator_stack,
+                                &mut expressions,
+                                1,
+                            ) {
+                                Ok(true) => last_token = Some(Category::PrefixOperators),
+                                Ok(false) => {}
+                                Err(error) => log.recover(error)?,
+                            }
+                        } else if operation.accepts_single_value() {
+                            // if value directly follows a single-argument function, push function to output
+                            if let Err(error) = operation.set_arguments(1) {
+                                log.recover(error)?;
+                            }
+                            expressions.push(operator_stack.pop().unwrap().expression.unwrap());
                         }
                     }
                 }
-                Category::Functions => operator_stack.push((rule, expression)),
+                // prefix operators have no left operand, so they never cause anything to be popped
+                Category::Functions | Category::PrefixOperators => {
+                    operator_stack.push(Operation::new(rule, expression, span))
+                }
                 Category::Operators | Category::ImplicitOperators => {
-                    while let Some((top_of_stack_rule, _)) = operator_stack.last() {
-                        if Category::OpenBrackets == top_of_stack_rule.category()
-                            || !(top_of_stack_rule.precedence() > rule.precedence()
-                                || (top_of_stack_rule.precedence() == rule.precedence()
+                    while let Some(top_of_stack) = operator_stack.last() {
+                        if Category::OpenBrackets == top_of_stack.rule.category()
+                            || !(top_of_stack.rule.precedence() > rule.precedence()
+                                || (top_of_stack.rule.precedence() == rule.precedence()
                                     && rule.left_associative()))
                         {
                             break;
                         }
-                        match operator_stack.pop() {
-                            Some((_, top_of_stack_expression)) => {
-                                if let Some(expr) = top_of_stack_expression {
-                                    expressions.push(expr)
+                        let operation = operator_stack.pop().unwrap();
+                        expressions.extend(operation.expression);
+                        // the operand of a prefix operator is complete, so it is the single value
+                        // of a function before it, e.g. sin -2 + 1 is sin(-2) + 1
+                        if operation.rule.category() == Category::PrefixOperators {
+                            if let Some(function) = operator_stack
+                                .last_mut()
+                                .filter(|function| function.accepts_single_value())
+                            {
+                                if let Err(error) = function.set_arguments(1) {
+                                    log.recover(error)?;
                                 }
+                                expressions.extend(operator_stack.pop().unwrap().expression);
                             }
-                            _ => (),
                         }
                     }
-                    operator_stack.push((rule, expression))
+                    operator_stack.push(Operation::new(rule, expression, span))
+                }
+                Category::PostfixOperators => {
+                    // the operand is already complete, so only operators that bind more tightly are output first
+                    while let Some(top_of_stack) = operator_stack.last() {
+                        if Category::OpenBrackets == top_of_stack.rule.category()
+                            || top_of_stack.rule.precedence() <= rule.precedence()
+                        {
+                            break;
+                        }
+                        if let Some(Operation {
+                            expression: Some(expr),
+                            ..
+                        }) = operator_stack.pop()
+                        {
+                            expressions.push(expr)
+                        }
+                    }
+                    // the text of some postfix operators is also their last argument, e.g. the exponent of x²
+                    if let Some(literal_format) = rule.literal_format() {
+                        match literal_format.parse::<T>(&matched_str) {
+                            Some(value) => expressions.push(Box::new(Number::new(value))),
+                            None => spanned_error!(
+                                ErrorCode::InvalidLiteral,
+                                span,
+                                "Could not parse '{}' as a number",
+                                matched_str
+                            ),
+                        }
+                    }
+                    expressions.push(expression.unwrap());
                 }
                 Category::Separators => {
-                    while let Some((top_of_stack_rule, _)) = operator_stack.last() {
-                        if Category::OpenBrackets == top_of_stack_rule.category() {
+                    while let Some(top_of_stack) = operator_stack.last() {
+                        if Category::OpenBrackets == top_of_stack.rule.category() {
                             break;
                         }
-                        match operator_stack.pop() {
-                            Some((_, top_of_stack_expression)) => {
-                                if let Some(expr) = top_of_stack_expression {
-                                    expressions.push(expr)
-                                }
-                            }
-                            _ => (),
+                        if let Some(Operation {
+                            expression: Some(expr),
+                            ..
+                        }) = operator_stack.pop()
+                        {
+                            expressions.push(expr)
                         }
                     }
+                    // count the arguments of the enclosing bracket
+                    if let Some(bracket) = operator_stack.last_mut() {
+                        bracket.arguments += 1;
+                    }
                 }
                 Category::OpenBrackets => {
                     bracket_context.push(rule.bracket_context());
-                    operator_stack.push((rule, expression))
+                    let mut bracket = Operation::new(rule, expression, span);
+                    if let Some(group) = operator_stack
+                        .last()
+                        .and_then(|operation| operation.pending_groups().first())
+                        .filter(|group| group.context == rule.bracket_context())
+                    {
+                        bracket.separator = group.separator.as_deref();
+                    }
+                    operator_stack.push(bracket)
                 }
                 Category::CloseBrackets => {
                     /*
@@ -181,44 +1687,29 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
                             .pop()
                             .unwrap_or_else(|| rule.bracket_context() + 1)
                     {
-                        syntax_error!(
-                            "{} at position {} does not match last opening bracket",
-                            matched_str,
-                            position
-                        );
+                        return Err(mismatched_bracket(
+                            &operator_stack,
+                            equation_string,
+                            &matched_str,
+                            span,
+                        ));
                     }
-
-                    while operator_stack
-                        .last()
-                        .is_none_or(|val| val.0.category() != Category::OpenBrackets)
+                    if !operator_stack
+                        .iter()
+                        .any(|operation| operation.rule.category() == Category::OpenBrackets)
                     {
-                        let (_rule, expression) = match operator_stack.pop() {
-                            Some(x) => x,
-                            None => syntax_error!("Invalid closing bracket"),
-                        };
-                        if let Some(expr) = expression {
-                            expressions.push(expr);
-                        }
-                    }
-                    match operator_stack.pop() {
-                        None => syntax_error!("Closing bracket '{}' at position {} used without opening bracket first", matched_str, position),
-                        Some((rule, _expression)) => {
-                            if rule.category() != Category::OpenBrackets {
-                                syntax_error!("Closing bracket '{}' at position {} used without opening bracket first", matched_str, position);
-                            }
-                        }
+                        spanned_error!(
+                            ErrorCode::UnmatchedBracket,
+                            span,
+                            "Closing bracket '{}' used without opening bracket first",
+                            matched_str
+                        );
                     }
-                    if operator_stack
-                        .last()
-                        .is_some_and(|(rule, _)| rule.category() == Category::Functions)
-                    {
-                        if let Some(op) = operator_stack.pop().unwrap().1 {
-                            expressions.push(op);
-                        }
+                    if Self::close_bracket(&mut operator_stack, &mut expressions, log)? {
+                        last_token = Some(Category::PrefixOperators);
                     }
                 }
             }
-            position = equation_string.rfind(&remainder).unwrap_or(position);
         } /*
            *   // After the while loop, pop the remaining items from the operator stack into the output queue.
            *   while there are tokens on the operator stack:
@@ -226,42 +1717,288 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
            *       {assert the operator on top of the stack is not a (left) parenthesis}
            *       pop the operator from the operator stack onto the output queue
            */
-        while !operator_stack.is_empty() {
-            let (rule, expression) = operator_stack.pop().unwrap();
-            if rule.category() == Category::OpenBrackets {
-                syntax_error!("Unclosed opening bracket")
+        if log.has_errors() {
+            Self::leave_out_dangling(&mut operator_stack, last_token);
+        }
+        while let Some(operation) = operator_stack.last() {
+            if operation.rule.category() == Category::OpenBrackets {
+                // parsing carries on as if the bracket were closed at the end of the expression
+                log.recover(unclosed_bracket(operation.span.clone()))?;
+                Self::close_bracket(&mut operator_stack, &mut expressions, log)?;
+                continue;
+            }
+            let operation = operator_stack.pop().unwrap();
+            if !operation.pending_groups().is_empty() {
+                log.recover(
+                    Error::new(
+                        ErrorType::SyntaxError,
+                        "Function is missing one or more argument groups".to_string(),
+                    )
+                    .with_code(ErrorCode::MissingArgumentGroup)
+                    .with_span(operation.span),
+                )?;
+            }
+            if let Some(expr) = operation.expression {
+                expressions.push(expr);
             }
-            if expression.is_some() {
-                expressions.push(expression.unwrap());
+        }
+        if expressions.is_empty() && log.has_errors() {
+            syntax_error!("Expression has no values left after leaving out the text with errors");
+        }
+
+        capture_arguments(expressions)
+    }
+
+    /// Outputs the operations inside the innermost bracket on the operator stack, then gives the bracketed values to
+    /// the function of the bracket itself and to the function the bracket is an argument group of, if any.
+    /// Returns true if a function is now waiting for its operand.
+    fn close_bracket(
+        operator_stack: &mut Vec<Operation<T>>,
+        expressions: &mut Expressions<T>,
+        log: &mut ParseLog,
+    ) -> Result<bool, Error> {
+        while let Some(operation) = operator_stack.pop() {
+            if operation.rule.category() != Category::OpenBrackets {
+                expressions.extend(operation.expression);
+                continue;
+            }
+            let mut bracket = operation;
+            // wrapping brackets such as |x| apply their function to the bracketed values,
+            // and conditional brackets such as \begin{cases} choose between them
+            if bracket.expression.is_some() {
+                if let Err(error) = bracket.set_arguments(bracket.arguments) {
+                    log.recover(error)?;
+                }
             }
+            expressions.extend(bracket.expression);
+            return match Self::close_argument_group(operator_stack, expressions, bracket.arguments)
+            {
+                Ok(operand_pending) => Ok(operand_pending),
+                Err(error) => log.recover(error).map(|_| false),
+            };
         }
+        Ok(false)
+    }
 
-        let equation = Equation::new(expressions, variables);
+    /// Skips the start of the string, at the byte offset given, up to the next token that is valid in the context or
+    /// that closes the innermost bracket, so parsing can carry on after an error there, e.g. to the 3 of # 3 after an
+    /// operator. An implicit operator is only valid to carry on from if the token it implies is valid after it.
+    /// Text in the skipped part that no rule matches is an error of its own, e.g. the second # of 2 # 3 # 4.
+    fn resynchronise(
+        &self,
+        remainder: &str,
+        position: usize,
+        context: &TokenContext,
+        scope: &Scope<T>,
+        log: &mut ParseLog,
+    ) -> Result<String, Error> {
+        let implied_context = TokenContext {
+            last_token: Some(Category::ImplicitOperators),
+            ..*context
+        };
+        let is_valid = |rest: &str| match self.match_next_token(rest, context, 0, scope) {
+            Ok((_, matched, remaining)) if matched.is_empty() => self
+                .match_next_token(&remaining, &implied_context, 0, scope)
+                .is_ok(),
+            Ok(_) => true,
+            Err(_) => false,
+        };
+        // the text with the error is not reported again
+        let mut index = remainder.chars().next().map_or(0, char::len_utf8);
+        let mut in_unknown = true;
+        while index < remainder.len() {
+            let rest = remainder[index..].trim_start();
+            if rest.len() < remainder.len() - index {
+                index = remainder.len() - rest.len();
+                in_unknown = false;
+                continue;
+            }
+            if is_valid(rest)
+                || context
+                    .open_bracket
+                    .is_some_and(|open| self.match_close_bracket(rest, open).is_some())
+            {
+                return Ok(rest.to_string());
+            }
+            // tokens only invalid after the error are skipped whole, and each run of unknown text is reported once
+            match self
+                .token_rules(scope)
+                .filter_map(|rule| rule.get_match(rest))
+                .map(|(matched, _)| matched.len())
+                .max()
+                .filter(|&length| length > 0)
+            {
+                Some(length) => {
+                    index += length;
+                    in_unknown = false;
+                }
+                None => {
+                    if !in_unknown {
+                        log.recover(self.unknown_token(
+                            context.statement,
+                            position + index,
+                            scope,
+                        ))?;
+                    }
+                    index += rest.chars().next().map_or(1, char::len_utf8);
+                    in_unknown = true;
+                }
+            }
+        }
+        Ok(String::new())
+    }
 
-        // run through equation to check for any syntax errors that were not caught by the rules
-        match equation.evaluate() {
-            Ok(_) => Ok(equation),
-            Err(e) => match e.error_type {
-                ErrorType::SyntaxError => Err(e),
-                _ => Ok(equation),
-            },
+    /// Leaves out the last token if it is an operator or separator that an error has left with nothing to apply to
+    fn leave_out_dangling(operator_stack: &mut Vec<Operation<T>>, last_token: Option<Category>) {
+        let top = match operator_stack.last_mut() {
+            Some(operation) => operation,
+            None => return,
+        };
+        match (last_token, top.rule.category()) {
+            (Some(Category::Separators), Category::OpenBrackets) => top.arguments -= 1,
+            (
+                Some(
+                    last @ (Category::Operators
+                    | Category::ImplicitOperators
+                    | Category::PrefixOperators),
+                ),
+                category,
+            ) if last == category => {
+                operator_stack.pop();
+            }
+            _ => {}
         }
     }
 
-    fn match_next_token(
+    /// Text of the closing bracket for the opening bracket of the given length at the start of the string, if the
+    /// closing bracket rule of its context is not a regex
+    fn closing_bracket(&self, equation_string: &str, length: usize) -> Option<&str> {
+        let open = self.syntax_rules.iter().find(|rule| {
+            rule.category() == Category::OpenBrackets
+                && rule
+                    .get_match(equation_string)
+                    .is_some_and(|(matched, _)| matched.len() == length)
+        })?;
+        self.syntax_rules
+            .iter()
+            .filter(|rule| {
+                rule.category() == Category::CloseBrackets
+                    && rule.bracket_context() == open.bracket_context()
+            })
+            .find_map(|rule| rule.literal_pattern())
+    }
+
+    /// Matches a closing bracket of the given bracket context at the start of the string
+    fn match_close_bracket<'b>(
         &self,
+        equation_string: &'b str,
+        context: i32,
+    ) -> Option<(&'b str, &'b str)> {
+        self.syntax_rules
+            .iter()
+            .filter(|rule| {
+                rule.category() == Category::CloseBrackets && rule.bracket_context() == context
+            })
+            .find_map(|rule| rule.get_match(equation_string))
+    }
+
+    /// Gives a group of arguments to the function at the top of the operator stack, if any, outputting the function
+    /// once it has all of its arguments. Returns true if the function is now waiting for its operand.
+    fn close_argument_group(
+        operator_stack: &mut Vec<Operation<T>>,
+        expressions: &mut Expressions<T>,
+        arguments: usize,
+    ) -> Result<bool, Error> {
+        let operation = match operator_stack.last_mut() {
+            Some(operation)
+                if operation.rule.category() == Category::Functions
+                    && !operation.operand_pending =>
+            {
+                operation
+            }
+            _ => return Ok(false),
+        };
+        // a capturing function is complete once it has all of its argument groups,
+        // any other function takes all arguments in the brackets
+        let complete = if operation.rule.arguments().is_empty() {
+            operation.set_arguments(arguments)?;
+            true
+        } else {
+            operation.captured += arguments;
+            operation.close_group()
+        };
+        if complete && !operation.rule.arguments().is_empty() {
+            let num_inputs = operation.captured + operation.rule.takes_operand() as usize;
+            if operation.rule.binds_variable()
+                || operation
+                    .expression
+                    .as_ref()
+                    .is_some_and(|expr| expr.num_inputs() != num_inputs)
+            {
+                operation.set_arguments(num_inputs)?;
+            }
+        }
+        if complete && operation.rule.takes_operand() {
+            // the function now applies to the expression that follows, like a prefix operator
+            operation.operand_pending = true;
+            return Ok(true);
+        } else if complete {
+            if let Some(op) = operator_stack.pop().unwrap().expression {
+                expressions.push(op);
+            }
+        }
+        Ok(false)
+    }
+
+    fn match_next_token<'b>(
+        &'b self,
         equation_string: &str,
-        last_token: &Option<Category>,
+        context: &TokenContext,
         position: usize,
-    ) -> Result<(Box<Rule<T>>, String, String), Error> {
+        scope: &'b Scope<T>,
+    ) -> Result<(&'b Rule<T>, String, String), Error> {
         // find all rules that match the next token of the equation
+        let last_token = &context.last_token;
+
+        // opening brackets of the argument groups that may come next, up to and including the first required group
+        // groups with a prefix may only be opened straight after their prefix
+        // any of these groups may also be a single value without brackets, e.g. the n in sum_(i=1)^n or the 4 of \sqrt 4
+        let mut group_contexts = Vec::new();
+        let mut bare_allowed = false;
+        for group in context.pending_groups {
+            if group.prefix.is_none() || context.group_prefixed {
+                group_contexts.push(group.context);
+                bare_allowed |= group.bare.unwrap_or(false);
+            }
+            if context.group_prefixed || !group.optional.unwrap_or(false) {
+                break;
+            }
+        }
+        let group_required = context.group_prefixed
+            || context
+                .pending_groups
+                .iter()
+                .any(|group| !group.optional.unwrap_or(false));
 
         let mut invalid_rules = Vec::new();
         let mut valid_rules = Vec::new();
         // get all rules that match the given equation substring
-        for rule in self.syntax_rules.as_slice() {
+        for rule in self.token_rules(scope) {
             if let Some((matched, other)) = rule.get_match(equation_string) {
-                let context_valid = rule.can_follow(*last_token);
+                let context_valid = if rule.category() == Category::OpenBrackets
+                    && group_contexts.contains(&rule.bracket_context())
+                {
+                    true
+                } else if group_required {
+                    rule.category() == Category::Fluff
+                        || (bare_allowed && is_value(rule.category()))
+                } else {
+                    // closing brackets are only valid if they close the innermost open bracket,
+                    // which disambiguates brackets that open and close with the same token, e.g. |x|
+                    rule.can_follow(*last_token)
+                        && (rule.category() != Category::CloseBrackets
+                            || context.open_bracket == Some(rule.bracket_context()))
+                };
                 if context_valid {
                     valid_rules.push((rule, matched, other.trim()));
                 } else if rule.category() != Category::ImplicitOperators {
@@ -277,17 +2014,51 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
             .filter(|(_, matched, _)| !matched.is_empty())
             .count()
             > 0;
-        let mut matching_rules: Vec<&(&Box<Rule<T>>, &str, &str)> = valid_rules
+        // Function, operator and constant names are reserved, so they may not also be matched as a variable
+        let reserved_names: Vec<&str> = valid_rules
+            .iter()
+            .filter(|(rule, _, _)| {
+                matches!(
+                    rule.category(),
+                    Category::Functions | Category::Constants | Category::PrefixOperators
+                )
+            })
+            .map(|(_, matched, _)| *matched)
+            .collect();
+        // A bracket that closes the innermost bracket comes before a longer operator starting with it,
+        // so |-2||3| is |-2|·|3| rather than an or
+        let closing = valid_rules
+            .iter()
+            .find(|(rule, _, _)| rule.category() == Category::CloseBrackets)
+            .map(|(_, matched, _)| *matched);
+        let mut matching_rules: Vec<&(&Rule<T>, &str, &str)> = valid_rules
             .iter()
             .filter(|(_, matched, _)| !has_valid_non_implicit_rules || !matched.is_empty())
+            .filter(|(rule, matched, _)| {
+                rule.category() != Category::Variables || !reserved_names.contains(matched)
+            })
+            .filter(|(rule, matched, _)| {
+                rule.category() != Category::Operators
+                    || !closing.is_some_and(|close| {
+                        matched.len() > close.len() && matched.starts_with(close)
+                    })
+            })
             .collect();
 
         if matching_rules.len() == 1 {
             // exactly one valid matching rule - can return straight away
             let rule = matching_rules[0];
-            return Ok((rule.0.clone(), rule.1.to_string(), rule.2.to_string()));
+            return Ok((rule.0, rule.1.to_string(), rule.2.to_string()));
         } else if matching_rules.is_empty() {
             // no valid rules - generate helpful error message
+            if group_required {
+                spanned_error!(
+                    ErrorCode::MissingArgumentGroup,
+                    unknown_span(equation_string, position),
+                    "Expected opening bracket of function argument group before '{}'",
+                    equation_string
+                )
+            }
             let last_token_str = if last_token.is_none() {
                 "start of equation"
             } else {
@@ -295,30 +2066,52 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
             };
             match invalid_rules.len() {
                 // string doesn't match any rule regex
-                0 => {
-                    syntax_error!(
-                        "No registered rules match start of expression '{}' at position {}",
-                        equation_string,
-                        position
-                    )
-                }
+                0 => return Err(self.unknown_token(context.statement, position, scope)),
                 // one rule matches but context was not valid
                 1 => {
                     let rule = invalid_rules[0];
-                    syntax_error!(
-                        "{} {} rule may not appear after {} at position {}",
+                    let span = position..position + rule.1.len();
+                    // a closing bracket that closes nothing may have an implicit operator matched before it, e.g. 2 + 3)
+                    if rule.0.category() == Category::CloseBrackets
+                        && (rule.0.can_follow(*last_token)
+                            || *last_token == Some(Category::ImplicitOperators))
+                    {
+                        if context.open_bracket.is_none() {
+                            spanned_error!(
+                                ErrorCode::UnmatchedBracket,
+                                span,
+                                "Closing bracket '{}' used without opening bracket first",
+                                rule.1
+                            );
+                        }
+                        spanned_error!(
+                            ErrorCode::UnmatchedBracket,
+                            span,
+                            "{} does not match last opening bracket",
+                            rule.1
+                        );
+                    }
+                    spanned_error!(
+                        ErrorCode::InvalidContext,
+                        span,
+                        "{} {} rule may not appear after {}",
                         format!("'{}'", rule.1),
                         rule.0.category(),
-                        last_token_str,
-                        position
+                        last_token_str
                     )
                 }
                 // multiple rules matched but none had valid context
                 _ => {
-                    syntax_error!(
-                        "Multiple rules match start of '{}' at position {} but none may appear after {}",
+                    let length = invalid_rules
+                        .iter()
+                        .map(|(_, matched, _)| matched.len())
+                        .max()
+                        .unwrap_or(0);
+                    spanned_error!(
+                        ErrorCode::InvalidContext,
+                        position..position + length,
+                        "Multiple rules match start of '{}' but none may appear after {}",
                         equation_string,
-                        position,
                         last_token_str
                     )
                 }
@@ -334,42 +2127,239 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
 
         // Of the shortlist ordered by priority, pick the first matched rule for which the next token is valid
         while let Some((matching_rule, matched_text, remaining_equation)) = matching_rules.pop() {
-            for rule in self.syntax_rules.as_slice() {
+            // the letters of a name that may not go here are not split into variables, e.g. the s of sin^2 x
+            if matching_rule.category() == Category::Variables
+                && reserved_names
+                    .iter()
+                    .any(|name| name.len() > matched_text.len())
+            {
+                continue;
+            }
+            // if nothing follows, the rule must be able to end the expression
+            if remaining_equation.is_empty() {
+                if matching_rule.allowed_at_end() {
+                    return Ok((*matching_rule, matched_text.to_string(), String::new()));
+                }
+                continue;
+            }
+            // the prefix of an argument group is not a token, but may follow its function, e.g. the _ in sum_(i=1)^n
+            if starts_argument_group(matching_rule, remaining_equation) {
+                return Ok((
+                    *matching_rule,
+                    matched_text.to_string(),
+                    remaining_equation.to_string(),
+                ));
+            }
+            // an operator that is also a postfix operator does not take a signed operand, so 50%+1 is 0.5 + 1
+            // while 7 % 3 is still 7 mod 3
+            let postfix_alternative = matching_rule.category() == Category::Operators
+                && valid_rules.iter().any(|(rule, matched, _)| {
+                    rule.category() == Category::PostfixOperators && *matched == *matched_text
+                });
+            let operator_follows = postfix_alternative
+                && self.token_rules(scope).any(|rule| {
+                    rule.category() == Category::Operators && rule.matches(remaining_equation)
+                });
+            for rule in self.token_rules(scope) {
                 // check if next token matches this rule
                 if !rule.matches(remaining_equation) {
                     continue;
                 }
+                if operator_follows && rule.category() == Category::PrefixOperators {
+                    continue;
+                }
                 // check if next token context is valid
                 if !rule.can_follow(Some(matching_rule.category())) {
                     continue;
                 }
 
                 return Ok((
-                    (*matching_rule).clone(),
+                    *matching_rule,
                     matched_text.to_string(),
                     remaining_equation.to_string(),
                 ));
             }
         }
 
-        syntax_error!(
-            "Expression '{}' at position {} does not match any registered rule",
-            equation_string,
-            position
+        let reserved = valid_rules
+            .iter()
+            .filter(|(_, matched, _)| reserved_names.contains(matched))
+            .max_by_key(|(_, matched, _)| matched.len());
+        if let Some((rule, matched, remaining)) = reserved {
+            let message = match remaining.is_empty() {
+                true => format!(
+                    "'{}' {} rule may not end the expression",
+                    matched,
+                    rule.category()
+                ),
+                false => format!(
+                    "'{}' {} rule may not be followed by '{}'",
+                    matched,
+                    rule.category(),
+                    remaining
+                ),
+            };
+            let error = Error::new(ErrorType::SyntaxError, message)
+                .with_code(ErrorCode::InvalidContext)
+                .with_span(position..position + matched.len());
+            // a power written on a function name, e.g. sin^2 x, is ambiguous with the inverse, e.g. sin^-1 x
+            if rule.category() == Category::Functions && remaining.starts_with('^') {
+                return Err(error.with_help(format!(
+                    "write the power after the argument instead, e.g. ({} x)^2",
+                    matched
+                )));
+            }
+            return Err(error);
+        }
+        spanned_error!(
+            ErrorCode::InvalidContext,
+            unknown_span(equation_string, position),
+            "Expression '{}' does not match any registered rule",
+            equation_string
         );
     }
 
+    /// Rules that match tokens within an expression, including functions defined by a program
+    fn token_rules<'b>(&'b self, scope: &'b Scope<T>) -> impl Iterator<Item = &'b Rule<T>> {
+        self.syntax_rules
+            .iter()
+            .chain(scope.rules.iter())
+            .filter(|rule| rule.is_token())
+    }
+
+    /// Error for text at the given byte offset of the statement that no rule matches, suggesting rules spelt like
+    /// the whole word it is in, e.g. SUM for the second M of SUMM(A1)
+    fn unknown_token(&self, statement: &str, position: usize, scope: &Scope<T>) -> Error {
+        let text = &statement.trim_end()[position..];
+        Error::new(
+            ErrorType::SyntaxError,
+            format!("No registered rules match start of expression '{}'", text),
+        )
+        .with_code(ErrorCode::UnknownToken)
+        .with_span(unknown_span(text, position))
+        .with_suggestions(self.similar_names(word_at(statement, position), scope, false))
+    }
+
+    /// Warning for a variable token named almost the same as a function, e.g. sinn, or None if it is not
+    fn misspelt_function(&self, token: &Token, scope: &Scope<T>) -> Option<Error> {
+        // shorter names are too often close to a function by chance, e.g. x1 and ln
+        if token.category != Category::Variables || token.text.chars().count() < 3 {
+            return None;
+        }
+        let functions = self.similar_names(&token.text, scope, true);
+        let function = functions.first()?;
+        Some(
+            Error::new(
+                ErrorType::Warning,
+                format!(
+                    "Variable '{}' is named almost the same as function '{}'",
+                    token.text, function
+                ),
+            )
+            .with_code(ErrorCode::MisspeltFunction)
+            .with_span(token.span.clone())
+            .with_suggestions(functions),
+        )
+    }
+
+    /// Names of rules that differ from the word by one or two edits, closest first. These are the text of rules
+    /// whose pattern is not a regex, and the binding labels of the other rules
+    fn similar_names(&self, word: &str, scope: &Scope<T>, functions_only: bool) -> Vec<String> {
+        let length = word.chars().count();
+        if length < 2 {
+            return Vec::new();
+        }
+        let max_distance = if length < 6 { 1 } else { 2 };
+        let word = word.to_lowercase();
+        // names that are binding labels sort after rule text of the same distance
+        let mut candidates: Vec<(usize, bool, String)> = Vec::new();
+        for rule in self.token_rules(scope) {
+            if functions_only && rule.category() != Category::Functions {
+                continue;
+            }
+            let (name, is_label) = match (rule.literal_pattern(), binding_label(rule)) {
+                (Some(pattern), _) => (pattern.to_string(), false),
+                (None, Some(label)) => (label, true),
+                (None, None) => continue,
+            };
+            // operators and brackets are single symbols, which any other symbol is one edit from
+            if !name.chars().any(char::is_alphabetic)
+                || candidates
+                    .iter()
+                    .any(|(_, _, candidate)| candidate.eq_ignore_ascii_case(&name))
+            {
+                continue;
+            }
+            let distance = edit_distance(&word, &name.to_lowercase());
+            if distance > 0 && distance <= max_distance {
+                candidates.push((distance, is_label, name));
+            }
+        }
+        candidates.sort();
+        candidates
+            .into_iter()
+            .take(3)
+            .map(|(_, _, name)| name)
+            .collect()
+    }
+
+    /// Matches a variable at the start of the string, e.g. the x of the differential dx
+    fn match_variable<'b>(&self, equation_string: &'b str) -> Option<(&Rule<T>, &'b str, &'b str)> {
+        self.syntax_rules
+            .iter()
+            .filter(|rule| rule.category() == Category::Variables)
+            .filter_map(|rule| {
+                let (matched, remaining) = rule.get_match(equation_string)?;
+                Some((rule, matched, remaining))
+            })
+            .max_by_key(|(_, matched, _)| matched.len())
+    }
+
+    /// Creates a variable, which shares its value with every other variable of the same name
+    fn create_variable(
+        label: &str,
+        variables: &mut HashMap<String, Rc<RefCell<T>>>,
+    ) -> Box<dyn Expression<ExprType = T>> {
+        if !variables.contains_key(label) {
+            variables.insert(
+                label.to_string(),
+                Rc::new(RefCell::new(
+                    <Variable<T> as Expression>::ExprType::from(0.0).unwrap(),
+                )),
+            );
+        }
+
+        Box::new(Variable::new(label, variables.get(label).unwrap()))
+    }
+
+    /// Creates the expression for a token at the given byte offset in the equation, if the rule has one
     fn create_expression(
         &self,
         rule: &Rule<T>,
         token: &str,
         variables: &mut HashMap<String, Rc<RefCell<T>>>,
+        scope: &Scope<T>,
+        position: usize,
     ) -> Result<Option<Box<dyn Expression<ExprType = T>>>, Error> {
         match rule.category() {
             // Rules that produce an Expression of type Function
-            Category::ImplicitOperators | Category::Operators | Category::Functions => {
+            Category::ImplicitOperators
+            | Category::Operators
+            | Category::PostfixOperators
+            | Category::PrefixOperators
+            | Category::Functions => {
                 match rule.binding() {
+                    None if rule.user_function().is_some() => Ok(Some(Box::new(
+                        UserFunctionCall::new(&scope.functions[rule.user_function().unwrap()]),
+                    ))),
+                    None if rule.conditional().is_some() => Ok(Some(Box::new(Conditional::new(
+                        rule.conditional().unwrap(),
+                    )))),
+                    Some(ref bind) if rule.binds_variable() => {
+                        Ok(Some(Box::new(Series::new(bind.0.clone()))))
+                    }
                     Some(ref bind) => Ok(Some(Box::new(bind.0.clone()))),
+                    None if rule.has_differential() => Ok(Some(Box::new(DefiniteIntegral::new()))),
                     None => {
                         return_error!(ErrorType::InternalError, "Syntax rule '{}' is of functional type but has no function binding set {}", token, rule.category());
                     }
@@ -384,37 +2374,225 @@ impl<T: NumericType<ExprType = T>> Parser<T> {
                 }
             }
             // Rules that produce an Expression of type Number
-            Category::Literals => match token.parse::<T>() {
-                Ok(value) => Ok(Some(Box::new(Number::new(value)))),
-                Err(_) => {
-                    return_error!(
-                        ErrorType::SyntaxError,
-                        "Could not parse literal '{}' as a number",
-                        token
-                    );
-                }
+            Category::Literals => match rule.literal_format().unwrap_or_default().parse::<T>(token)
+            {
+                Some(value) => Ok(Some(Box::new(Number::new(value)))),
+                None => spanned_error!(
+                    ErrorCode::InvalidLiteral,
+                    position..position + token.len(),
+                    "Could not parse literal '{}' as a number",
+                    token
+                ),
             },
             // Rules that produce an Expression of type Variable
-            Category::Variables => {
-                if !variables.contains_key(token) {
-                    variables.insert(
-                        token.to_string(),
-                        Rc::new(RefCell::new(
-                            <Variable<T> as Expression>::ExprType::from(0.0).unwrap(),
-                        )),
-                    );
-                }
-
-                Ok(Some(Box::new(Variable::new(
-                    token,
-                    variables.get(token).unwrap(),
-                ))))
-            }
+            Category::Variables => match rule.reference() {
+                // a range is expanded into a variable for each cell by the parser
+                Some(Reference::CellRange) => Ok(None),
+                Some(reference) => match reference.labels(token) {
+                    Some(labels) => Ok(Some(Self::create_variable(&labels[0], variables))),
+                    None => spanned_error!(
+                        ErrorCode::InvalidReference,
+                        position..position + token.len(),
+                        "'{}' is not a valid cell reference",
+                        token
+                    ),
+                },
+                None => Ok(Some(Self::create_variable(
+                    &rule.variable_mode().label(token),
+                    variables,
+                ))),
+            },
+            // Opening brackets that wrap a function, e.g. |x|
+            Category::OpenBrackets => match rule.binding() {
+                Some(ref bind) => Ok(Some(Box::new(bind.0.clone()))),
+                None => match rule.conditional() {
+                    Some(order) => Ok(Some(Box::new(Conditional::new(order)))),
+                    None => Ok(None),
+                },
+            },
             // Rules that do not correspond to an Expression
             Category::CloseBrackets
-            | Category::OpenBrackets
             | Category::Separators
-            | Category::Fluff => Ok(None),
+            | Category::Fluff
+            | Category::Assignments
+            | Category::StatementSeparators => Ok(None),
+        }
+    }
+}
+
+/// Byte offset of a slice of a string from the start of the string
+fn subslice_start(string: &str, slice: &str) -> usize {
+    slice.as_ptr() as usize - string.as_ptr() as usize
+}
+
+/// Span of the first character of text no rule matches, given the byte offset of the text in the equation
+fn unknown_span(text: &str, position: usize) -> Range<usize> {
+    position..position + text.chars().next().map_or(0, char::len_utf8)
+}
+
+/// Gives a syntax error found by evaluating an expression the code and span of the whole expression,
+/// e.g. for 3 , 4 which leaves two values
+fn leftover_values(error: Error, equation_string: &str) -> Error {
+    if error.code.is_some() {
+        return error;
+    }
+    let start = equation_string.len() - equation_string.trim_start().len();
+    error
+        .with_code(ErrorCode::ArityMismatch)
+        .with_span(start..equation_string.trim_end().len())
+        .with_help("all but one value must be used as arguments of operators or functions")
+}
+
+/// Error for an opening bracket, with the given span, that is never closed
+fn unclosed_bracket(span: Range<usize>) -> Error {
+    Error::new(
+        ErrorType::SyntaxError,
+        "Unclosed opening bracket".to_string(),
+    )
+    .with_code(ErrorCode::UnmatchedBracket)
+    .with_span(span)
+    .with_help("add a closing bracket after the bracketed values")
+}
+
+/// Error for a closing bracket, with the given text and span, of a different kind to the last opening bracket
+fn mismatched_bracket<T: NumericType>(
+    operator_stack: &[Operation<T>],
+    equation_string: &str,
+    matched_str: &str,
+    span: Range<usize>,
+) -> Error {
+    let error = Error::new(
+        ErrorType::SyntaxError,
+        format!("{} does not match last opening bracket", matched_str),
+    )
+    .with_code(ErrorCode::UnmatchedBracket);
+    let error = match operator_stack
+        .iter()
+        .rfind(|operation| operation.rule.category() == Category::OpenBrackets)
+    {
+        Some(bracket) => error.with_note(format!(
+            "the last opening bracket is '{}'",
+            &equation_string[bracket.span.clone()]
+        )),
+        None => error,
+    };
+    error.with_span(span)
+}
+
+/// Moves the span of an error in the width-normalised string onto the same characters of the original string
+fn restore_span(mut error: Error, original: &str, normalised: &str) -> Error {
+    error.span = error
+        .span
+        .map(|span| restore_range(span, original, normalised));
+    error
+}
+
+/// Moves a byte range of the width-normalised string onto the same characters of the original string
+fn restore_range(range: Range<usize>, original: &str, normalised: &str) -> Range<usize> {
+    let restore = |offset: usize| {
+        let chars = normalised[..offset.min(normalised.len())].chars().count();
+        original
+            .char_indices()
+            .nth(chars)
+            .map_or(original.len(), |(index, _)| index)
+    };
+    restore(range.start)..restore(range.end)
+}
+
+/// Whether the string starts with the prefix of an argument group the rule may take next
+fn starts_argument_group<T: NumericType>(rule: &Rule<T>, equation_string: &str) -> bool {
+    for group in rule.arguments() {
+        if let Some(prefix) = &group.prefix {
+            if equation_string.starts_with(prefix.as_str()) {
+                return true;
+            }
+        }
+        if !group.optional.unwrap_or(false) {
+            break;
+        }
+    }
+    false
+}
+
+/// Byte offset in the equation of a remainder left after matching tokens from its start
+fn offset(equation_string: &str, remainder: &str) -> usize {
+    equation_string.trim_end().len() - remainder.len()
+}
+
+/// Label of the function a rule applies, if it has one
+fn binding_label<T: NumericType>(rule: &Rule<T>) -> Option<String> {
+    rule.binding()
+        .as_ref()
+        .map(|(function, _)| function.label.to_string())
+}
+
+/// The name at the start of text no rule matches, including the backslash of a LaTeX command, e.g. \sinn of \sinn{x}
+fn leading_word(text: &str) -> &str {
+    let start = if text.starts_with('\\') { 1 } else { 0 };
+    let end = text[start..]
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .map_or(text.len(), |i| start + i);
+    &text[..end]
+}
+
+/// The word of the text that the byte offset is in, if it is at a letter, e.g. SUMM for the second M of SUMM(A1)
+fn word_at(text: &str, position: usize) -> &str {
+    let end = position + leading_word(&text[position..]).len();
+    if end == position || text[position..].starts_with('\\') {
+        return &text[position..end];
+    }
+    let start = text[..position]
+        .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_')
+        .len();
+    let start = match text[..start].ends_with('\\') {
+        true => start - 1,
+        false => start,
+    };
+    &text[start..end]
+}
+
+/// Number of characters inserted, removed, replaced or swapped with the next to turn one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between the first i characters of a and the first j of b
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    distances[0] = (0..=b.len()).collect();
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
         }
     }
+    distances[a.len()][b.len()]
+}
+
+/// Whether a token of the category is a single value
+fn is_value(category: Category) -> bool {
+    matches!(
+        category,
+        Category::Literals | Category::Constants | Category::Variables
+    )
+}
+
+/// Replaces full-width forms, as typed by East Asian input methods, with the ASCII characters they stand for,
+/// as NFKC normalisation would, e.g. ２ｘ＋１ becomes 2x+1
+fn normalise_width(string: &str) -> String {
+    string
+        .chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap_or(c),
+            '\u{3000}' => ' ',
+            _ => c,
+        })
+        .collect()
 }
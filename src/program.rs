@@ -0,0 +1,104 @@
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::{
+    equation::{Equation, Value},
+    error::{return_error, Error, ErrorType},
+    NumericType,
+};
+
+/// A statement of a program, optionally assigning its value to a variable used by later statements
+pub(crate) struct Statement<T: NumericType> {
+    pub target: Option<(String, Rc<RefCell<T>>)>,
+    pub equation: Equation<T>,
+}
+
+pub struct Program<T: NumericType> {
+    // evaluated in order, with the value of the program being the value of the last statement
+    statements: Vec<Statement<T>>,
+    variable_names: Vec<String>,
+    // holds Rcs for each input of the program, which are also held by the statements that use them
+    variables: HashMap<String, Rc<RefCell<T>>>,
+}
+
+impl<T: NumericType<ExprType = T>> Program<T> {
+    pub(crate) fn new(
+        statements: Vec<Statement<T>>,
+        variables: HashMap<String, Rc<RefCell<T>>>,
+    ) -> Program<T> {
+        let variable_names = variables.keys().cloned().collect();
+        Program {
+            statements,
+            variable_names,
+            variables,
+        }
+    }
+
+    /// Evaluates each statement in turn, assigning values to variables as it goes,
+    /// and returns the value of the last statement
+    pub fn evaluate(&self) -> Value<T> {
+        let mut value = None;
+        for statement in &self.statements {
+            let result = statement.equation.evaluate()?;
+            if let Some((label, cell)) = &statement.target {
+                match cell.try_borrow_mut() {
+                    Ok(mut variable) => *variable = result,
+                    Err(..) => return_error!(
+                        ErrorType::VariableAccessError,
+                        "Variable '{}' already mutably borrowed",
+                        label
+                    ),
+                }
+            }
+            value = Some(result);
+        }
+        match value {
+            Some(value) => Ok(value),
+            None => return_error!(ErrorType::NotInitialisedError, "Program is empty"),
+        }
+    }
+
+    /// Gets an input of the program, which is any variable used before it is assigned
+    pub fn variable(&self, label: &str) -> Result<RefMut<'_, T>, Error> {
+        match self.variables.get(label) {
+            Some(var_cell) => match var_cell.try_borrow_mut() {
+                Ok(var) => Ok(var),
+                Err(..) => return_error!(
+                    ErrorType::VariableAccessError,
+                    "Variable '{}' already mutably borrowed",
+                    label
+                ),
+            },
+            None => return_error!(
+                ErrorType::VariableAccessError,
+                "Program does not have input '{}'",
+                label
+            ),
+        }
+    }
+
+    pub fn variables(&self) -> &[String] {
+        &self.variable_names
+    }
+
+    /// Names of the variables assigned by the program, in the order they are first assigned
+    pub fn assignments(&self) -> Vec<&str> {
+        let mut assigned: Vec<&str> = Vec::new();
+        for statement in &self.statements {
+            if let Some((label, _)) = &statement.target {
+                if !assigned.contains(&label.as_str()) {
+                    assigned.push(label);
+                }
+            }
+        }
+        assigned
+    }
+}
+
+impl<T: NumericType> Debug for Program<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "program({})", self.variable_names.join(", "))
+    }
+}
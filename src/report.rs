@@ -0,0 +1,32 @@
+use std::ops::Range;
+
+use crate::{equation::Equation, error::Error, NumericType};
+
+/// The result of parsing an equation that carries on after errors, as given by `Parser::parse_recovering`
+pub struct ParseReport<T: NumericType> {
+    /// the equation, if the parts of it without errors still form one. With no errors it is the equation
+    /// `Parser::parse` gives
+    pub equation: Option<Equation<T>>,
+    /// every error found, in the order of where they are in the equation
+    pub errors: Vec<Error>,
+    /// parts of the equation that parsed but are probably mistakes, e.g. a variable sinn named almost the same as
+    /// the function sin
+    pub warnings: Vec<Error>,
+    /// changes made to an unfinished equation so that it could be parsed, as given by `Parser::parse_lenient`
+    pub repairs: Vec<Repair>,
+}
+
+/// A change made to the end of an unfinished equation, with byte offsets in the equation as given
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repair {
+    /// a token at the end that needs something after it was left out, e.g. the + of 2 +
+    DroppedToken { text: String, span: Range<usize> },
+    /// a closing bracket was added at the end for the opening bracket with the given span, e.g. the ) of sin(x
+    ClosedBracket { text: String, opening: Range<usize> },
+}
+
+impl<T: NumericType> ParseReport<T> {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
@@ -0,0 +1,48 @@
+use super::LiteralFormat;
+use crate::NumericType;
+
+impl LiteralFormat {
+    /// Converts the text of a literal to a number, returning None if it is not valid in this format
+    pub(crate) fn parse<T: NumericType>(&self, token: &str) -> Option<T> {
+        match self {
+            LiteralFormat::Decimal | LiteralFormat::Scientific => token.parse().ok(),
+            LiteralFormat::DigitSeparated => {
+                if token.starts_with('_') || token.ends_with('_') || token.contains("__") {
+                    return None;
+                }
+                token.replace('_', "").parse().ok()
+            }
+            LiteralFormat::DecimalComma => token.replace("{,}", ".").replace(',', ".").parse().ok(),
+            LiteralFormat::Hexadecimal => parse_integer(token, "0x", 16),
+            LiteralFormat::Octal => parse_integer(token, "0o", 8),
+            LiteralFormat::Binary => parse_integer(token, "0b", 2),
+            LiteralFormat::Superscript => token
+                .chars()
+                .map(|c| match c {
+                    '⁺' => Some('+'),
+                    '⁻' => Some('-'),
+                    '¹' => Some('1'),
+                    '²' => Some('2'),
+                    '³' => Some('3'),
+                    '⁰' | '⁴'..='⁹' => char::from_digit(c as u32 - '⁰' as u32, 10),
+                    _ => None,
+                })
+                .collect::<Option<String>>()?
+                .parse()
+                .ok(),
+        }
+    }
+}
+
+/// Parses an integer in the given base, after an optional prefix and ignoring underscores between digits
+fn parse_integer<T: NumericType>(token: &str, prefix: &str, radix: u32) -> Option<T> {
+    let digits = match token.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => &token[prefix.len()..],
+        _ => token,
+    };
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    let digits: String = digits.chars().filter(|&c| c != '_').collect();
+    T::from(u128::from_str_radix(&digits, radix).ok()?)
+}
@@ -1,4 +1,7 @@
+mod literal;
+pub(crate) mod reference;
 pub(crate) mod ruleset;
+mod variable;
 
 use serde::Deserialize;
 use std::{
@@ -13,15 +16,35 @@ use crate::{error::return_error, Error, ErrorType};
 pub enum Syntax {
     Standard,
     LaTeX,
+    AsciiMath,
+    Spreadsheet,
+    Rpn,
+    SExpr,
+}
+
+/// Where operators and functions are written relative to their arguments
+#[derive(PartialEq, Copy, Clone, Default)]
+pub enum Notation {
+    /// between or around their arguments, with brackets and precedence deciding the order, e.g. (3 + 4) * 2
+    #[default]
+    Infix,
+    /// after their arguments, e.g. 3 4 + 2 *
+    Postfix,
+    /// first in a bracketed list of their arguments, e.g. (* (+ 3 4) 2)
+    Prefix,
 }
 
 /// The type of expression a Rule represents
-#[derive(PartialEq, Copy, Clone, Eq, Hash, Deserialize)]
+#[derive(PartialEq, Copy, Clone, Eq, Hash, Deserialize, Debug)]
 pub enum Category {
     /// an operation on two values, e.g. +, *, ^
     Operators,
     /// an operation on two values, e.g. +, *, ^ that matches no characters and is implied by context
     ImplicitOperators,
+    /// an operation on the value before it, e.g. the ! in 3!
+    PostfixOperators,
+    /// an operation on the value after it, e.g. the - in -x
+    PrefixOperators,
     /// a function of 1 or more arguments, e.g. sin, ln
     Functions,
     /// a number such as 2, -0.5 etc
@@ -38,6 +61,10 @@ pub enum Category {
     Separators,
     /// tokens with no syntactic meaning that can be ignored
     Fluff,
+    /// separator between the variable and value of an assignment in a program, e.g. the = in r = 2x
+    Assignments,
+    /// separator between the statements of a program, e.g. ;
+    StatementSeparators,
 }
 
 /// The order in which operations with equal precedence should be resolved
@@ -47,7 +74,76 @@ pub enum Associativity {
     RightToLeft,
 }
 
-#[derive(Deserialize)]
+/// Which of each pair of arguments to a conditional is the condition
+#[derive(Copy, Clone, Deserialize, PartialEq)]
+pub enum ConditionOrder {
+    /// condition then value, e.g. if(x > 0, x, 0)
+    ConditionFirst,
+    /// value then condition, e.g. \begin{cases} x & x > 0 \\ 0 & \text{otherwise} \end{cases}
+    ValueFirst,
+}
+
+/// How the text of a Literals rule is converted to a number
+#[derive(Copy, Clone, Deserialize, PartialEq, Default)]
+pub enum LiteralFormat {
+    /// digits with an optional decimal point, e.g. 3.14
+    #[default]
+    Decimal,
+    /// a decimal number with an optional exponent after an e, e.g. 6.02e23
+    Scientific,
+    /// a decimal or scientific number with underscores between digits, e.g. 1_000_000
+    DigitSeparated,
+    /// a decimal number with a comma as the decimal point, written {,} in LaTeX, e.g. 3,14
+    DecimalComma,
+    /// an integer in base 16, with an optional 0x prefix and underscores between digits, e.g. 0xFF
+    Hexadecimal,
+    /// an integer in base 8, with an optional 0o prefix and underscores between digits, e.g. 0o17
+    Octal,
+    /// an integer in base 2, with an optional 0b prefix and underscores between digits, e.g. 0b1010
+    Binary,
+    /// an integer written in superscript digits with an optional sign, e.g. the ⁻¹ of x⁻¹
+    Superscript,
+}
+
+/// How the text of a Variables rule refers to the cells of a spreadsheet, which are variables named like B3
+#[derive(Copy, Clone, Deserialize, PartialEq)]
+pub enum Reference {
+    /// a single cell, optionally with $ marking the column or row absolute, e.g. $B$3
+    Cell,
+    /// a rectangle of cells between two corners, e.g. A1:B3, expanded into an argument for each cell
+    CellRange,
+}
+
+/// How the text matched by a Variables rule is divided into variables
+#[derive(Copy, Clone, Deserialize, PartialEq, Default)]
+pub enum VariableMode {
+    /// the whole match is one variable, e.g. xy
+    #[default]
+    Identifier,
+    /// each letter is a variable, with an optional subscript of digits or a single letter, so letters written together
+    /// are joined by ImplicitOperators rules, e.g. xy is x*y and x1y is x1*y
+    SingleLetter,
+    /// the whole match is one variable with a LaTeX accent, subscript or primes, named in one form however it is
+    /// written, e.g. x_{1} is x_1 and \hat x is \hat{x}
+    Decorated,
+}
+
+/// A bracketed group that supplies arguments to a function, e.g. each {...} in \frac{a}{b}
+#[derive(Deserialize, Clone, Default)]
+pub struct ArgumentDefinition {
+    /// bracket context of the group, matching the `context` of an OpenBrackets rule
+    pub context: i32,
+    /// if true, the group may be left out, in which case the rule's `default_binding` is used
+    pub optional: Option<bool>,
+    /// text that must come directly before the opening bracket, e.g. the ^ in \sum_{i=1}^{n}
+    pub prefix: Option<String>,
+    /// text that splits the group into several arguments, e.g. the = in \sum_{i=1}^{n}
+    pub separator: Option<String>,
+    /// if true, the group may instead be a single literal, constant or variable without brackets, e.g. the n in sum_(i=1)^n
+    pub bare: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
 pub struct RuleDefinition {
     pub pattern: Option<String>,
     pub pattern_is_regex: Option<bool>,
@@ -56,6 +152,16 @@ pub struct RuleDefinition {
     pub binding: Option<String>,
     pub may_follow: Option<Vec<Category>>,
     pub context: Option<i32>,
+    pub arguments: Option<Vec<ArgumentDefinition>>,
+    pub default_binding: Option<String>,
+    pub binds_variable: Option<bool>,
+    pub operand: Option<bool>,
+    pub differential: Option<String>,
+    pub conditional: Option<ConditionOrder>,
+    pub literal_format: Option<LiteralFormat>,
+    pub reference: Option<Reference>,
+    pub case_sensitive: Option<bool>,
+    pub variable_mode: Option<VariableMode>,
 }
 
 #[derive(Deserialize)]
@@ -63,19 +169,26 @@ pub struct RuleCategoryDefinition {
     pub default_associativity: Option<Associativity>,
     pub default_precedence: Option<u32>,
     pub may_follow: Vec<Category>,
+    pub case_sensitive: Option<bool>,
+    pub variable_mode: Option<VariableMode>,
     pub rules: Vec<RuleDefinition>,
 }
 
 #[derive(Deserialize)]
-pub struct RuleCollectionDefinition(
-    #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
-    pub  HashMap<Category, RuleCategoryDefinition>,
-);
+pub struct RuleCollectionDefinition {
+    pub case_sensitive: Option<bool>,
+    #[serde(flatten, with = "::serde_with::rust::maps_duplicate_key_is_error")]
+    pub categories: HashMap<Category, RuleCategoryDefinition>,
+}
 
 fn builtin_rulesets() -> &'static [(Syntax, &'static str)] {
     &[
         (Syntax::Standard, include_str!("json/standard.json")),
         (Syntax::LaTeX, include_str!("json/latex.json")),
+        (Syntax::AsciiMath, include_str!("json/asciimath.json")),
+        (Syntax::Spreadsheet, include_str!("json/spreadsheet.json")),
+        (Syntax::Rpn, include_str!("json/rpn.json")),
+        (Syntax::SExpr, include_str!("json/sexpr.json")),
     ]
 }
 
@@ -87,6 +200,14 @@ pub(crate) fn get_builtin_ruleset(syntax: &Syntax) -> Option<&'static str> {
     }
 }
 
+pub(crate) fn get_builtin_notation(syntax: &Syntax) -> Notation {
+    match syntax {
+        Syntax::Rpn => Notation::Postfix,
+        Syntax::SExpr => Notation::Prefix,
+        _ => Notation::Infix,
+    }
+}
+
 pub fn get_definitions(syntax: Syntax) -> Result<RuleCollectionDefinition, Error> {
     let json = match get_builtin_ruleset(&syntax) {
         Some(json) => json,
@@ -115,6 +236,8 @@ impl fmt::Display for Category {
         match self {
             Self::Operators => write!(f, "Operators"),
             Self::ImplicitOperators => write!(f, "Implicit Operators"),
+            Self::PostfixOperators => write!(f, "Postfix Operators"),
+            Self::PrefixOperators => write!(f, "Prefix Operators"),
             Self::Functions => write!(f, "Functions"),
             Self::Literals => write!(f, "Literals"),
             Self::Constants => write!(f, "Constants"),
@@ -123,6 +246,8 @@ impl fmt::Display for Category {
             Self::CloseBrackets => write!(f, "Closing Brackets"),
             Self::Separators => write!(f, "Separators"),
             Self::Fluff => write!(f, "Fluff"),
+            Self::Assignments => write!(f, "Assignments"),
+            Self::StatementSeparators => write!(f, "Statement Separators"),
         }
     }
 }
@@ -131,12 +256,12 @@ impl Deref for RuleCollectionDefinition {
     type Target = HashMap<Category, RuleCategoryDefinition>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.categories
     }
 }
 
 impl DerefMut for RuleCollectionDefinition {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.categories
     }
 }
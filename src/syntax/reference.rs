@@ -0,0 +1,62 @@
+use super::Reference;
+
+/// Largest number of cells a range may refer to, as each cell becomes a variable
+const MAX_RANGE_CELLS: usize = 10_000;
+
+impl Reference {
+    /// Gets the names of the variables referred to by the text of a reference, returning None if it is not valid
+    pub(crate) fn labels(&self, token: &str) -> Option<Vec<String>> {
+        match self {
+            Reference::Cell => {
+                let (column, row) = parse_cell(token)?;
+                Some(vec![cell_label(column, row)])
+            }
+            Reference::CellRange => {
+                let (start, end) = token.split_once(':')?;
+                let (start_column, start_row) = parse_cell(start)?;
+                let (end_column, end_row) = parse_cell(end)?;
+                let columns = start_column.min(end_column)..=start_column.max(end_column);
+                let rows = start_row.min(end_row)..=start_row.max(end_row);
+                if columns.clone().count() * rows.clone().count() > MAX_RANGE_CELLS {
+                    return None;
+                }
+                // cells are listed row by row, as a spreadsheet reads them
+                Some(
+                    rows.flat_map(|row| columns.clone().map(move |column| cell_label(column, row)))
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+/// Gets the zero-based column and row of a cell reference such as B3, ignoring any $ marking it absolute
+pub(crate) fn parse_cell(reference: &str) -> Option<(usize, usize)> {
+    let reference = reference.replace('$', "");
+    let (letters, digits) = reference.split_at(reference.find(|c: char| c.is_ascii_digit())?);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    // columns are numbered A to Z, then AA to AZ and so on
+    let column = letters.chars().try_fold(0usize, |column, c| {
+        column
+            .checked_mul(26)?
+            .checked_add((c.to_ascii_uppercase() as u8 - b'A') as usize + 1)
+    })? - 1;
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let row = digits.parse::<usize>().ok()?.checked_sub(1)?;
+    Some((column, row))
+}
+
+/// Name of the variable of a cell, e.g. B3 for column 1 and row 2
+fn cell_label(column: usize, row: usize) -> String {
+    let mut letters = Vec::new();
+    let mut remaining = column + 1;
+    while remaining > 0 {
+        letters.push((b'A' + ((remaining - 1) % 26) as u8) as char);
+        remaining = (remaining - 1) / 26;
+    }
+    letters.iter().rev().collect::<String>() + &(row + 1).to_string()
+}
@@ -1,9 +1,9 @@
 pub(crate) mod rule;
 
-use super::{Associativity, Category};
+use super::{Associativity, Category, VariableMode};
 use crate::{
     bindings::FunctionBindings,
-    error::{return_error, Error, ErrorType},
+    error::{return_error, Error, ErrorCode, ErrorType},
     expressions::function::Function,
     syntax::RuleCollectionDefinition,
     NumericType,
@@ -11,13 +11,18 @@ use crate::{
 use regex::Regex;
 use rule::Rule;
 
-pub(crate) struct Ruleset<T: NumericType>(Vec<Box<Rule<T>>>);
+pub(crate) struct Ruleset<T: NumericType> {
+    rules: Vec<Rule<T>>,
+    // whether names defined by programs, which have no rule of their own, match case exactly
+    case_sensitive: bool,
+}
 
 impl<T: NumericType<ExprType = T> + FunctionBindings> Ruleset<T> {
     pub fn create(rule_definitions: RuleCollectionDefinition) -> Result<Ruleset<T>, Error> {
-        let mut rules: Vec<Box<Rule<T>>> = Vec::new();
+        let mut rules: Vec<Rule<T>> = Vec::new();
 
-        for (category, category_def) in rule_definitions.0 {
+        let collection_case_sensitive = rule_definitions.case_sensitive.unwrap_or(false);
+        for (category, category_def) in rule_definitions.categories {
             for rule_def in category_def.rules {
                 if rule_def.pattern.is_none() {
                     if category != Category::ImplicitOperators {
@@ -30,8 +35,19 @@ impl<T: NumericType<ExprType = T> + FunctionBindings> Ruleset<T> {
                     )
                 }
                 let json_pattern = rule_def.pattern.unwrap_or(String::new());
+                // the text of a pattern that is not a regex, which can be suggested in place of a misspelt token
+                let literal_pattern = match rule_def.pattern_is_regex {
+                    None | Some(false) if !json_pattern.is_empty() => Some(json_pattern.clone()),
+                    _ => None,
+                };
+                // a rule's setting overrides its category's, which overrides the collection's
+                let case_sensitive = rule_def
+                    .case_sensitive
+                    .or(category_def.case_sensitive)
+                    .unwrap_or(collection_case_sensitive);
                 let pattern = match Regex::new(&format!(
-                    r"^(?i)({})(.*)",
+                    r"^{}({})(.*)",
+                    if case_sensitive { "" } else { "(?i)" },
                     match rule_def.pattern_is_regex {
                         None | Some(false) => regex::escape(&json_pattern),
                         Some(true) => json_pattern,
@@ -46,37 +62,166 @@ impl<T: NumericType<ExprType = T> + FunctionBindings> Ruleset<T> {
                         )
                     }
                 };
+                if rule_def.literal_format.is_some()
+                    && !matches!(category, Category::Literals | Category::PostfixOperators)
+                {
+                    return_error!(
+                        ErrorType::RuleParseError,
+                        "Only Literals and PostfixOperators rules support field 'literal_format'"
+                    )
+                }
+                if rule_def.reference.is_some() && category != Category::Variables {
+                    return_error!(
+                        ErrorType::RuleParseError,
+                        "Only Variables rules support field 'reference'"
+                    )
+                }
+                let variable_mode = rule_def.variable_mode.or(category_def.variable_mode);
+                if variable_mode.is_some() && category != Category::Variables {
+                    return_error!(
+                        ErrorType::RuleParseError,
+                        "Only Variables rules support field 'variable_mode'"
+                    )
+                }
+                if variable_mode == Some(VariableMode::SingleLetter) && rule_def.reference.is_some()
+                {
+                    return_error!(
+                        ErrorType::RuleParseError,
+                        "Rules with field 'reference' do not support single letter variables"
+                    )
+                }
                 let follows = rule_def
                     .may_follow
                     .unwrap_or_else(|| category_def.may_follow.clone());
-                rules.push(Box::new(match category {
-                    Category::Literals => Rule::new_literal_rule(pattern, follows),
-                    Category::Variables => Rule::new_variable_rule(pattern, follows),
-                    Category::Separators | Category::Fluff => {
+                rules.push(match category {
+                    Category::Literals => Rule::new_literal_rule(pattern, follows, rule_def.literal_format.unwrap_or_default()),
+                    Category::Variables => Rule::new_variable_rule(pattern, follows, rule_def.reference)
+                        .with_variable_mode(variable_mode.unwrap_or_default()),
+                    Category::Separators | Category::Fluff | Category::Assignments | Category::StatementSeparators => {
                         Rule::new_non_expression_rule(
                             pattern,
-                            category.clone(),
+                            category,
                             follows,
                         )
                     }
                     Category::CloseBrackets | Category::OpenBrackets => {
-                        match rule_def.context {
-                            Some(i) => Rule::new_bracket_rule(pattern, category.clone(), follows, i),
+                        let context = match rule_def.context {
+                            Some(i) => i,
                             None => return_error!(ErrorType::RuleParseError, "Parenthesis rules require integer field 'context'"),
+                        };
+                        // an opening bracket with a binding wraps its contents in a function, e.g. |x|
+                        match (category, rule_def.binding) {
+                            (_, Some(_)) if rule_def.conditional.is_some() => return_error!(ErrorType::RuleParseError, "Rules with field 'conditional' do not support field 'binding'"),
+                            (Category::OpenBrackets, None) if rule_def.conditional.is_some() => {
+                                Rule::new_conditional_rule(pattern, category, follows, context, rule_def.conditional.unwrap())
+                            }
+                            (Category::CloseBrackets, None) if rule_def.conditional.is_some() => return_error!(
+                                ErrorType::RuleParseError,
+                                "Conditionals must be set on the OpenBrackets rule, not the CloseBrackets rule"
+                            ),
+                            (Category::OpenBrackets, Some(label)) => {
+                                let binding = Self::lookup_fixed_binding(&label, 1)?;
+                                Rule::new_wrapping_rule(pattern, follows, context, binding)
+                            }
+                            (Category::CloseBrackets, Some(_)) => return_error!(
+                                ErrorType::RuleParseError,
+                                "Wrapping function bindings must be set on the OpenBrackets rule, not the CloseBrackets rule"
+                            ),
+                            _ => Rule::new_bracket_rule(pattern, category, follows, context),
+                        }
+                    }
+                    // conditionals take pairs of conditions and values as arguments, e.g. if(x > 0, x, 0)
+                    Category::Functions if rule_def.conditional.is_some() => {
+                        if rule_def.binding.is_some() || rule_def.arguments.is_some() || rule_def.binds_variable.is_some() || rule_def.operand.is_some() || rule_def.differential.is_some() {
+                            return_error!(ErrorType::RuleParseError, "Rules with field 'conditional' do not support fields 'binding', 'arguments', 'binds_variable', 'operand' or 'differential'")
                         }
+                        Rule::new_conditional_rule(pattern, category, follows, 0, rule_def.conditional.unwrap())
                     }
-                    Category::Constants | Category::Functions | Category::Operators | Category::ImplicitOperators => {
+                    // integrals take their bounds from bracket groups, followed by a body ended by a differential, e.g. \int_{0}^{1} x \, dx
+                    Category::Functions if rule_def.differential.is_some() => {
+                        let arguments = match rule_def.arguments {
+                            Some(arguments) if arguments.len() == 2 && arguments.iter().all(|arg| !arg.optional.unwrap_or(false) && arg.separator.is_none()) => arguments,
+                            _ => return_error!(ErrorType::RuleParseError, "Rules with field 'differential' require two required argument groups for the lower and upper bounds, without separators"),
+                        };
+                        if rule_def.binding.is_some() || rule_def.binds_variable.is_some() {
+                            return_error!(ErrorType::RuleParseError, "Rules with field 'differential' do not support fields 'binding' or 'binds_variable'")
+                        }
+                        let differential = match Regex::new(&format!(r"^(?:{})", rule_def.differential.unwrap())) {
+                            Ok(re) => re,
+                            Err(e) => return_error!(ErrorType::RuleParseError, "Rule differential is not a valid regex: {}", e),
+                        };
+                        let precedence = match rule_def.precedence {
+                            Some(n) => n,
+                            None => return_error!(ErrorType::RuleParseError, "Field 'precedence' is required for rules with field 'differential'"),
+                        };
+                        Rule::new_integral_rule(pattern, precedence, arguments, differential, follows)
+                    }
+                    // functions that take their arguments from consecutive bracket groups, e.g. \frac{a}{b}
+                    Category::Functions if rule_def.arguments.is_some() => {
+                        let binding = match rule_def.binding {
+                            Some(s) => Self::lookup_binding(&s)?, _ => return_error!(ErrorType::RuleParseError, "Function, Operator and Constant rules require string field 'binding'"),
+                        };
+                        let arguments = rule_def.arguments.unwrap_or_default();
+                        let required = arguments.iter().filter(|arg| !arg.optional.unwrap_or(false)).count();
+                        let operand = rule_def.operand.unwrap_or(false);
+                        let binds_variable = rule_def.binds_variable.unwrap_or(false);
+                        // groups split by a separator supply a varying number of arguments, so are checked when parsed
+                        let num_inputs = arguments.len() + operand as usize;
+                        let fixed_inputs = arguments.iter().all(|arg| arg.separator.is_none());
+                        if !binds_variable && fixed_inputs && !binding.arity.accepts(num_inputs) {
+                            return_error!(ErrorType::RuleParseError, "Binding '{}' takes {} arguments but rule defines {} arguments", binding.label, binding.arity, num_inputs)
+                        }
+                        let binding = binding.with_inputs(num_inputs);
+                        let default_binding = match rule_def.default_binding {
+                            Some(label) => {
+                                let default_binding = Self::lookup_binding(&label)?;
+                                if !default_binding.arity.accepts(required + operand as usize) {
+                                    return_error!(ErrorType::RuleParseError, "Default binding '{}' takes {} arguments but rule defines {} required arguments", label, default_binding.arity, required + operand as usize)
+                                }
+                                Some(default_binding.with_inputs(required + operand as usize))
+                            }
+                            None if required != arguments.len() => return_error!(ErrorType::RuleParseError, "Rules with optional argument groups require string field 'default_binding'"),
+                            None => None,
+                        };
+                        // a function with an operand binds to it like a prefix operator, so needs a precedence
+                        let precedence = match (rule_def.precedence, operand) {
+                            (Some(n), _) => n,
+                            (None, false) => 0,
+                            (None, true) => return_error!(ErrorType::RuleParseError, "Field 'precedence' is required for rules with field 'operand'"),
+                        };
+                        let rule = Rule::new_capturing_rule(pattern, precedence, binding, default_binding, arguments, operand, follows);
+                        match binds_variable {
+                            true => rule.with_bound_variable(),
+                            false => rule,
+                        }
+                    }
+                    Category::Constants | Category::Functions | Category::Operators | Category::ImplicitOperators | Category::PostfixOperators | Category::PrefixOperators => {
+                        if rule_def.arguments.is_some() {
+                            return_error!(ErrorType::RuleParseError, "Only Functions rules support field 'arguments'")
+                        }
+                        if rule_def.operand.is_some() {
+                            return_error!(ErrorType::RuleParseError, "Only Functions rules with field 'arguments' support field 'operand'")
+                        }
+                        if rule_def.differential.is_some() {
+                            return_error!(ErrorType::RuleParseError, "Only Functions rules support field 'differential'")
+                        }
+                        if rule_def.conditional.is_some() {
+                            return_error!(ErrorType::RuleParseError, "Only Functions and OpenBrackets rules support field 'conditional'")
+                        }
+                        if rule_def.binds_variable.is_some() && category != Category::Functions {
+                            return_error!(ErrorType::RuleParseError, "Only Functions rules support field 'binds_variable'")
+                        }
                         let binding = match rule_def.binding {
                             Some(s) => s, _ => return_error!(ErrorType::RuleParseError, "Function, Operator and Constant rules require string field 'binding'"),
                         };
-
-                        let binding_opt: Option<Function<T>> = <T as FunctionBindings>::get_binding(&binding);
-                        let binding: Function<T> = match binding_opt {
-                            Some(f) => f,
-                            _ => return_error!(
-                                ErrorType::RuleParseError,
-                                "No binding found with label '{}' and type {}", binding, std::any::type_name::<T>()
-                            ),
+                        // functions have their number of inputs set when parsed, the other categories have a fixed number
+                        let binding = match category {
+                            Category::Functions => Self::lookup_binding(&binding)?,
+                            Category::Constants => Self::lookup_fixed_binding(&binding, 0)?,
+                            // a postfix operator with a literal format also takes the number it matches as an argument, e.g. x²
+                            Category::PostfixOperators if rule_def.literal_format.is_some() => Self::lookup_fixed_binding(&binding, 2)?,
+                            Category::PostfixOperators | Category::PrefixOperators => Self::lookup_fixed_binding(&binding, 1)?,
+                            _ => Self::lookup_fixed_binding(&binding, 2)?,
                         };
                         let associativity = rule_def.associativity.unwrap_or(category_def.default_associativity.unwrap_or(Associativity::LeftToRight));
                         let precedence = match category_def.default_precedence {
@@ -84,32 +229,83 @@ impl<T: NumericType<ExprType = T> + FunctionBindings> Ruleset<T> {
                             None => match rule_def.precedence {
                                 Some(n) => n,
                                 None => match category {
-                                    Category::Operators | Category::ImplicitOperators => {return_error!(ErrorType::RuleParseError, "Field 'precedence' is required for Operator rules")}
+                                    Category::Operators | Category::ImplicitOperators | Category::PostfixOperators | Category::PrefixOperators => {return_error!(ErrorType::RuleParseError, "Field 'precedence' is required for Operator rules")}
                                     _ => 0,
                                 }
                             }
                         };
-                        Rule::new_function_rule(
+                        let rule = Rule::new_function_rule(
                             pattern,
                             precedence,
-                            category.clone(),
+                            category,
                             associativity,
                             binding,
                             follows,
-                        )
+                        );
+                        let rule = match rule_def.literal_format {
+                            Some(literal_format) => rule.with_literal_argument(literal_format),
+                            None => rule,
+                        };
+                        match rule_def.binds_variable.unwrap_or(false) {
+                            true => rule.with_bound_variable(),
+                            false => rule,
+                        }
                     }
-                }));
+                }.with_literal_pattern(literal_pattern));
             }
         }
 
-        return Ok(Ruleset(rules));
+        Ok(Ruleset {
+            rules,
+            case_sensitive: collection_case_sensitive,
+        })
+    }
+
+    /// Whether names that are not matched by a rule of the collection, such as functions defined by programs,
+    /// match case exactly
+    pub fn case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// Looks up a binding that must accept the given number of inputs
+    fn lookup_fixed_binding(label: &str, num_inputs: usize) -> Result<Function<T>, Error> {
+        let binding = Self::lookup_binding(label)?;
+        if !binding.arity.accepts(num_inputs) {
+            return_error!(
+                ErrorType::RuleParseError,
+                "Binding '{}' takes {} arguments but is used where {} are required",
+                label,
+                binding.arity,
+                num_inputs
+            )
+        }
+        Ok(binding.with_inputs(num_inputs))
+    }
+
+    fn lookup_binding(label: &str) -> Result<Function<T>, Error> {
+        match <T as FunctionBindings>::get_binding(label) {
+            Some(f) => Ok(f),
+            None => Err(Error::new(
+                ErrorType::RuleParseError,
+                format!(
+                    "No binding found with label '{}' and type {}",
+                    label,
+                    std::any::type_name::<T>()
+                ),
+            )
+            .with_code(ErrorCode::UnknownBinding)
+            .with_help(format!(
+                "register a binding labelled '{}' with bindings::register_bindings before creating the parser",
+                label
+            ))),
+        }
     }
 }
 
 impl<T: NumericType> core::ops::Deref for Ruleset<T> {
-    type Target = Vec<Box<Rule<T>>>;
+    type Target = Vec<Rule<T>>;
 
-    fn deref(self: &'_ Self) -> &'_ Self::Target {
-        &self.0
+    fn deref(&self) -> &Self::Target {
+        &self.rules
     }
 }
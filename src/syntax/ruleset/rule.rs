@@ -1,4 +1,7 @@
-use crate::syntax::{Associativity, Category};
+use crate::syntax::{
+    ArgumentDefinition, Associativity, Category, ConditionOrder, LiteralFormat, Reference,
+    VariableMode,
+};
 use crate::{expressions::function::Function, NumericType};
 use regex::{Captures, Regex};
 
@@ -10,14 +13,27 @@ pub(crate) struct Rule<T: NumericType> {
     binding: Option<(Function<T>, Associativity)>,
     follows: Vec<Category>,
     context: i32,
+    arguments: Vec<ArgumentDefinition>,
+    default_binding: Option<Function<T>>,
+    binds_variable: bool,
+    operand: bool,
+    differential: Option<Regex>,
+    conditional: Option<ConditionOrder>,
+    // index of the definition of a function defined by a program, in the scope of the program
+    user_function: Option<usize>,
+    // format of the literal matched by a literal rule, or the last argument of a postfix operator such as ²
+    literal_format: Option<LiteralFormat>,
+    // spreadsheet cells referred to by a variable rule, e.g. A1:A3
+    reference: Option<Reference>,
+    // whether a variable rule matches a whole identifier or a single letter, e.g. the x of xy
+    variable_mode: VariableMode,
+    // the text matched by a rule whose pattern is not a regex, e.g. sin
+    literal_pattern: Option<String>,
 }
 
 impl<T: NumericType> Rule<T> {
-    pub fn new_non_expression_rule(
-        pattern: Regex,
-        category: Category,
-        follows: Vec<Category>,
-    ) -> Rule<T> {
+    /// A rule with the given pattern, category and categories it may follow, and no other properties
+    fn new(pattern: Regex, category: Category, follows: Vec<Category>) -> Rule<T> {
         Rule {
             pattern,
             precedence: 0,
@@ -25,9 +41,28 @@ impl<T: NumericType> Rule<T> {
             binding: None,
             follows,
             context: 0,
+            arguments: Vec::new(),
+            default_binding: None,
+            binds_variable: false,
+            operand: false,
+            differential: None,
+            conditional: None,
+            user_function: None,
+            literal_format: None,
+            reference: None,
+            variable_mode: VariableMode::Identifier,
+            literal_pattern: None,
         }
     }
 
+    pub fn new_non_expression_rule(
+        pattern: Regex,
+        category: Category,
+        follows: Vec<Category>,
+    ) -> Rule<T> {
+        Rule::new(pattern, category, follows)
+    }
+
     pub fn new_bracket_rule(
         pattern: Regex,
         category: Category,
@@ -35,12 +70,21 @@ impl<T: NumericType> Rule<T> {
         pair_context: i32,
     ) -> Rule<T> {
         Rule {
-            pattern,
-            precedence: 0,
-            category,
-            binding: None,
-            follows,
             context: pair_context,
+            ..Rule::new(pattern, category, follows)
+        }
+    }
+
+    pub fn new_wrapping_rule(
+        pattern: Regex,
+        follows: Vec<Category>,
+        pair_context: i32,
+        binding: Function<T>,
+    ) -> Rule<T> {
+        Rule {
+            binding: Some((binding, Associativity::LeftToRight)),
+            context: pair_context,
+            ..Rule::new(pattern, Category::OpenBrackets, follows)
         }
     }
 
@@ -53,41 +97,124 @@ impl<T: NumericType> Rule<T> {
         follows: Vec<Category>,
     ) -> Rule<T> {
         Rule {
-            pattern,
             precedence,
-            category,
-            binding: (Some((binding, associativity))),
-            follows,
-            context: 0,
+            binding: Some((binding, associativity)),
+            ..Rule::new(pattern, category, follows)
         }
     }
 
-    pub fn new_literal_rule(pattern: Regex, follows: Vec<Category>) -> Rule<T> {
+    pub fn new_capturing_rule(
+        pattern: Regex,
+        precedence: u32,
+        binding: Function<T>,
+        default_binding: Option<Function<T>>,
+        arguments: Vec<ArgumentDefinition>,
+        operand: bool,
+        follows: Vec<Category>,
+    ) -> Rule<T> {
         Rule {
-            pattern,
-            precedence: 0,
-            category: Category::Literals,
-            binding: None,
-            follows,
-            context: 0,
+            precedence,
+            binding: Some((binding, Associativity::LeftToRight)),
+            arguments,
+            default_binding,
+            operand,
+            ..Rule::new(pattern, Category::Functions, follows)
         }
     }
 
-    pub fn new_variable_rule(pattern: Regex, follows: Vec<Category>) -> Rule<T> {
+    /// An integral that takes its bounds from argument groups, then its body as an operand
+    /// ended by a differential such as dx
+    pub fn new_integral_rule(
+        pattern: Regex,
+        precedence: u32,
+        arguments: Vec<ArgumentDefinition>,
+        differential: Regex,
+        follows: Vec<Category>,
+    ) -> Rule<T> {
         Rule {
-            pattern,
-            precedence: 0,
-            category: Category::Variables,
-            binding: None,
-            follows,
-            context: 0,
+            precedence,
+            arguments,
+            operand: true,
+            differential: Some(differential),
+            ..Rule::new(pattern, Category::Functions, follows)
         }
     }
 
+    /// A function or opening bracket that chooses between its arguments by testing conditions,
+    /// e.g. if(x > 0, x, 0) or \begin{cases} x & x > 0 \\ 0 & \text{otherwise} \end{cases}
+    pub fn new_conditional_rule(
+        pattern: Regex,
+        category: Category,
+        follows: Vec<Category>,
+        pair_context: i32,
+        order: ConditionOrder,
+    ) -> Rule<T> {
+        Rule {
+            context: pair_context,
+            conditional: Some(order),
+            ..Rule::new(pattern, category, follows)
+        }
+    }
+
+    /// A function defined by an earlier statement of a program, e.g. the f in f(t) = t^2 + 1; f(2)
+    pub fn new_user_function_rule(pattern: Regex, follows: Vec<Category>, index: usize) -> Rule<T> {
+        Rule {
+            user_function: Some(index),
+            ..Rule::new(pattern, Category::Functions, follows)
+        }
+    }
+
+    /// Makes a postfix operator take the number written by its own text as its last argument, e.g. the 2 of x²
+    pub fn with_literal_argument(mut self, literal_format: LiteralFormat) -> Rule<T> {
+        self.literal_format = Some(literal_format);
+        self
+    }
+
+    /// Makes the function bind its first argument as an index variable, e.g. the i in sum(i, 1, n, i^2)
+    pub fn with_bound_variable(mut self) -> Rule<T> {
+        self.binds_variable = true;
+        self
+    }
+
+    pub fn new_literal_rule(
+        pattern: Regex,
+        follows: Vec<Category>,
+        literal_format: LiteralFormat,
+    ) -> Rule<T> {
+        Rule {
+            literal_format: Some(literal_format),
+            ..Rule::new(pattern, Category::Literals, follows)
+        }
+    }
+
+    pub fn new_variable_rule(
+        pattern: Regex,
+        follows: Vec<Category>,
+        reference: Option<Reference>,
+    ) -> Rule<T> {
+        Rule {
+            reference,
+            ..Rule::new(pattern, Category::Variables, follows)
+        }
+    }
+
+    /// Sets how a variable rule divides its match into variables, e.g. xy into x and y, and names them
+    pub fn with_variable_mode(mut self, variable_mode: VariableMode) -> Rule<T> {
+        self.variable_mode = variable_mode;
+        self
+    }
+
+    /// Sets the text the rule matches, for rules whose pattern is not a regex
+    pub fn with_literal_pattern(mut self, literal_pattern: Option<String>) -> Rule<T> {
+        self.literal_pattern = literal_pattern;
+        self
+    }
+
     pub fn allowed_at_start(&self) -> bool {
         match self.category {
             Category::Constants
             | Category::Functions
+            | Category::PrefixOperators
             | Category::Literals
             | Category::OpenBrackets
             | Category::Fluff
@@ -95,7 +222,10 @@ impl<T: NumericType> Rule<T> {
             Category::CloseBrackets
             | Category::Operators
             | Category::ImplicitOperators
-            | Category::Separators => false,
+            | Category::PostfixOperators
+            | Category::Separators
+            | Category::Assignments
+            | Category::StatementSeparators => false,
         }
     }
 
@@ -104,16 +234,28 @@ impl<T: NumericType> Rule<T> {
             Category::CloseBrackets
             | Category::Constants
             | Category::Literals
+            | Category::PostfixOperators
             | Category::Fluff
             | Category::Variables => true,
             Category::Functions
+            | Category::PrefixOperators
             | Category::OpenBrackets
             | Category::Operators
             | Category::ImplicitOperators
-            | Category::Separators => false,
+            | Category::Separators
+            | Category::Assignments
+            | Category::StatementSeparators => false,
         }
     }
 
+    /// whether the rule matches a token within an expression, rather than one that divides a program into statements
+    pub fn is_token(&self) -> bool {
+        !matches!(
+            self.category,
+            Category::Assignments | Category::StatementSeparators
+        )
+    }
+
     pub fn can_follow(&self, token: Option<Category>) -> bool {
         if self.category == Category::Fluff {
             return true;
@@ -137,11 +279,79 @@ impl<T: NumericType> Rule<T> {
         self.context
     }
 
+    /// argument groups taken by a capturing function such as \frac{a}{b}
+    pub fn arguments(&self) -> &[ArgumentDefinition] {
+        &self.arguments
+    }
+
+    pub fn default_binding(&self) -> &Option<Function<T>> {
+        &self.default_binding
+    }
+
+    /// whether the function takes an index variable, lower and upper bounds and a body evaluated for each index
+    pub fn binds_variable(&self) -> bool {
+        self.binds_variable
+    }
+
+    /// whether the function takes a final argument from the expression after its argument groups
+    pub fn takes_operand(&self) -> bool {
+        self.operand
+    }
+
+    pub fn has_differential(&self) -> bool {
+        self.differential.is_some()
+    }
+
+    /// matches the differential that ends the body of an integral, returning the text after it
+    pub fn match_differential<'a>(&self, eq_str: &'a str) -> Option<&'a str> {
+        let differential = self.differential.as_ref()?.find(eq_str)?;
+        Some(&eq_str[differential.end()..])
+    }
+
+    /// which argument of each pair is the condition, if the rule is a conditional
+    pub fn conditional(&self) -> Option<ConditionOrder> {
+        self.conditional
+    }
+
+    pub fn user_function(&self) -> Option<usize> {
+        self.user_function
+    }
+
+    /// which spreadsheet cells the text matched by a variable rule refers to
+    pub fn reference(&self) -> Option<Reference> {
+        self.reference
+    }
+
+    /// how the text matched by a variable rule is divided into variables and named
+    pub fn variable_mode(&self) -> VariableMode {
+        self.variable_mode
+    }
+
+    /// how the text matched by a literal rule, or the argument matched by a postfix operator, is converted to a number
+    pub fn literal_format(&self) -> Option<LiteralFormat> {
+        self.literal_format
+    }
+
+    pub fn literal_pattern(&self) -> Option<&str> {
+        self.literal_pattern.as_deref()
+    }
+
+    pub fn follows(&self) -> &[Category] {
+        &self.follows
+    }
+
     pub fn priority(&self) -> u32 {
         match self.category {
-            Category::OpenBrackets | Category::CloseBrackets | Category::Separators => 5,
+            Category::OpenBrackets
+            | Category::CloseBrackets
+            | Category::Separators
+            | Category::Assignments
+            | Category::StatementSeparators => 5,
             Category::Operators => 4,
-            Category::Functions | Category::Constants => 3,
+            Category::Functions
+            | Category::Constants
+            | Category::PostfixOperators
+            | Category::PrefixOperators => 3,
             Category::Literals => 2,
             Category::Variables => 1,
             Category::ImplicitOperators => 0,
@@ -169,6 +379,14 @@ impl<T: NumericType> Rule<T> {
 
     pub fn get_match<'a>(&self, eq_str: &'a str) -> Option<(&'a str, &'a str)> {
         let res: Captures<'a> = self.pattern.captures(eq_str)?;
-        Some((res.get(1)?.into(), res.get(2)?.into()))
+        let matched: &str = res.get(1)?.into();
+        match self.variable_mode {
+            VariableMode::SingleLetter => {
+                // the match starts at the beginning of the string, so the rest follows the letter
+                let length = self.variable_mode.match_length(matched);
+                Some((&eq_str[..length], &eq_str[length..]))
+            }
+            _ => Some((matched, res.get(2)?.into())),
+        }
     }
 }
@@ -0,0 +1,132 @@
+use std::borrow::Cow;
+
+use super::VariableMode;
+
+/// Commands that place an accent over a variable, e.g. \hat{x}
+const ACCENTS: &[&str] = &[
+    "widehat",
+    "widetilde",
+    "overline",
+    "hat",
+    "bar",
+    "vec",
+    "ddot",
+    "dot",
+    "tilde",
+    "check",
+    "breve",
+];
+
+impl VariableMode {
+    /// Length of the variable at the start of the text matched by a variable rule
+    pub(crate) fn match_length(&self, matched: &str) -> usize {
+        match self {
+            VariableMode::Identifier | VariableMode::Decorated => matched.len(),
+            VariableMode::SingleLetter => single_letter_length(matched),
+        }
+    }
+
+    /// Gets the name of the variable written by the text of a token, which is the same however it is spaced or braced
+    pub(crate) fn label<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        match self {
+            VariableMode::Identifier | VariableMode::SingleLetter => Cow::Borrowed(token),
+            VariableMode::Decorated => Cow::Owned(decorated_label(token)),
+        }
+    }
+}
+
+/// Length of the first letter of a variable and its subscript, if any, e.g. x, x1, x_1, x_i or x_{max}
+fn single_letter_length(matched: &str) -> usize {
+    let letter = match matched.chars().next() {
+        Some(c) => c.len_utf8(),
+        None => return 0,
+    };
+    let rest = &matched[letter..];
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let subscript = match rest.strip_prefix('_') {
+        Some(group) if group.starts_with('{') => match group.find('}') {
+            Some(end) => end + 2,
+            None => 0,
+        },
+        Some(group) if group.starts_with(|c: char| c.is_ascii_digit()) => digits(group) + 1,
+        Some(group) => match group.chars().next() {
+            Some(c) if c.is_alphabetic() => c.len_utf8() + 1,
+            _ => 0,
+        },
+        None => digits(rest),
+    };
+    letter + subscript
+}
+
+/// Splits a LaTeX command such as \alpha from the start of the text
+fn split_command(text: &str) -> Option<(&str, &str)> {
+    let name = text.strip_prefix('\\')?;
+    let end = name
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(name.len());
+    match end {
+        0 => None,
+        _ => Some(text.split_at(end + 1)),
+    }
+}
+
+/// Splits a braced group, command or single character from the start of the text, returning the group without
+/// braces or spaces, e.g. max from {max}
+fn split_group(text: &str) -> (String, &str) {
+    let text = text.trim_start();
+    if let Some(inner) = text.strip_prefix('{') {
+        let end = inner.find('}').unwrap_or(inner.len());
+        let group = inner[..end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        return (group, inner.get(end + 1..).unwrap_or(""));
+    }
+    if let Some((command, rest)) = split_command(text) {
+        return (command.to_string(), rest);
+    }
+    match text.chars().next() {
+        Some(c) => (c.to_string(), &text[c.len_utf8()..]),
+        None => (String::new(), text),
+    }
+}
+
+/// Writes a variable with an accent, subscript or primes in one form, with the accented letter in braces, a subscript
+/// in braces only if it is longer than one character and primes last, e.g. \hat{x}, x_1, v_{max} or \alpha_i'
+fn decorated_label(token: &str) -> String {
+    let token = token.trim();
+    let (base, mut rest) = match split_command(token) {
+        Some((command, after)) if ACCENTS.contains(&&command[1..]) => {
+            let (letter, after) = split_group(after);
+            (format!("{}{{{}}}", command, letter), after)
+        }
+        Some((command, after)) => (command.to_string(), after),
+        None => {
+            let end = token
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(token.len());
+            (token[..end].to_string(), &token[end..])
+        }
+    };
+    let mut subscript = None;
+    let mut primes = 0;
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('_') {
+            let (group, after) = split_group(after);
+            subscript = Some(group);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('\'') {
+            primes += 1;
+            rest = after;
+        } else {
+            break;
+        }
+    }
+    let subscript = match subscript {
+        Some(group) if group.chars().count() == 1 => format!("_{}", group),
+        Some(group) => format!("_{{{}}}", group),
+        None => String::new(),
+    };
+    format!("{}{}{}", base, subscript, "'".repeat(primes))
+}
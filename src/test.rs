@@ -2,6 +2,8 @@
 // bindings - add and use new bindings
 // create new syntax
 
+#![allow(clippy::excessive_precision, clippy::result_large_err)]
+
 extern crate whetstone;
 
 macro_rules! assert_near {
@@ -14,9 +16,17 @@ macro_rules! assert_near {
 
 #[cfg(test)]
 mod tests {
-    use whetstone::syntax::{self, Category, RuleDefinition};
-    use whetstone::{bindings, bindings::FunctionPointer, NumericType, Value};
-    use whetstone::{syntax::Syntax, Parser};
+    use whetstone::syntax::{self, Category, LiteralFormat, RuleDefinition, VariableMode};
+    use whetstone::{
+        bindings,
+        bindings::{Arity, DerivativePointer, FunctionPointer},
+        calculus::{IntegrationOptions, Term},
+        Error, ErrorCode, ErrorType, NumericType, Repair, Value,
+    };
+    use whetstone::{
+        syntax::{Notation, Syntax},
+        Parser,
+    };
 
     #[test]
     fn test_standard_syntax() {
@@ -138,6 +148,686 @@ mod tests {
         assert_near!(5.0, eq.evaluate().unwrap());
     }
 
+    #[test]
+    fn test_decorated_variables() {
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+
+        // each way of writing a variable gives the same name
+        for (equation, label) in [
+            ("x_1 + x_{ 1 }", "x_1"),
+            ("v_{max} + v_{ max }", "v_{max}"),
+            ("\\alpha_i + \\alpha_{i}", "\\alpha_i"),
+            ("\\hat x + \\hat{ x }", "\\hat{x}"),
+            ("x' \\cdot x'", "x'"),
+            (
+                "\\bar{\\theta}_0'' + \\bar\\theta_{0}''",
+                "\\bar{\\theta}_0''",
+            ),
+        ] {
+            let eq = factory.parse(equation).unwrap();
+            assert_eq!(vec![label.to_string()], eq.variables(), "{equation}");
+            *eq.variable(label).unwrap() = 2.0;
+            assert_eq!(4.0, eq.evaluate().unwrap(), "{equation}");
+        }
+
+        let eq = factory
+            .parse("\\frac{1}{2} m v_{max}^2 + x_1 x' + \\Delta_t")
+            .unwrap();
+        let mut variables = eq.variables().to_vec();
+        variables.sort();
+        assert_eq!(vec!["\\Delta_t", "m", "v_{max}", "x'", "x_1"], variables);
+        for (label, value) in [
+            ("m", 2.0),
+            ("v_{max}", 3.0),
+            ("x_1", 4.0),
+            ("x'", 5.0),
+            ("\\Delta_t", 1.0),
+        ] {
+            *eq.variable(label).unwrap() = value;
+        }
+        assert_eq!(30.0, eq.evaluate().unwrap());
+
+        // subscripts of functions still take argument groups
+        let eq = factory.parse("\\sum_{i=1}^{3} x_i + \\log_{2}{8}").unwrap();
+        *eq.variable("x_i").unwrap() = 1.0;
+        assert_eq!(6.0, eq.evaluate().unwrap());
+
+        // a subscript is one character unless it is braced
+        assert_eq!(vec!["x_{12}"], factory.parse("x_{12}").unwrap().variables());
+        factory
+            .parse("x_12")
+            .expect_err("literals may not follow variables");
+
+        // programs assign to the same name
+        let program = factory
+            .parse_program("v_{0} := 2; t_{ max } := 3; v_0 t_{max}")
+            .unwrap();
+        assert_eq!(6.0, program.evaluate().unwrap());
+    }
+
+    #[test]
+    fn test_asciimath_syntax() {
+        let factory = Parser::<f64>::new(Syntax::AsciiMath).unwrap();
+
+        assert_eq!(1.0, factory.parse("x+1").unwrap().evaluate().unwrap());
+
+        let eq = factory.parse("sqrt(x)/2 + x^(2/3)").unwrap();
+        assert_eq!(1, eq.variables().len());
+        *eq.variable("x").unwrap() = 64.0;
+        assert_eq!(20.0, eq.evaluate().unwrap());
+
+        // single letters are separate variables, implicitly multiplied, while function names are matched whole
+        let eq = factory.parse("2xy + sinx").unwrap();
+        assert_eq!(2, eq.variables().len());
+        *eq.variable("x").unwrap() = 0.5;
+        *eq.variable("y").unwrap() = 3.0;
+        assert_near!(3.0 + 0.5f64.sin(), eq.evaluate().unwrap());
+
+        let eq = factory.parse("alpha xx beta -: theta * 2 ** 2").unwrap();
+        assert_eq!(3, eq.variables().len());
+        *eq.variable("alpha").unwrap() = 3.0;
+        *eq.variable("beta").unwrap() = 4.0;
+        *eq.variable("theta").unwrap() = 6.0;
+        assert_eq!(8.0, eq.evaluate().unwrap());
+
+        let eq = factory.parse("sum_(i=1)^n i^2 + prod_{i=1}^{3} i").unwrap();
+        assert_eq!(1, eq.variables().len());
+        *eq.variable("n").unwrap() = 4.0;
+        assert_eq!(36.0, eq.evaluate().unwrap());
+
+        for (equation, expected) in [
+            ("abs(-3) + |x - 5| + |__2.5__| + |~2.5~|", 11.0),
+            ("floor(2.5) + ceil(2.5) + (:x:)", 7.0),
+            ("frac{1}{4} + frac 1 2 + root(3)(8)", 2.75),
+            ("log_2 8 + log_(3)(9) + log(100) + ln(e)", 8.0),
+            ("exp(1) + exp x - e^x", std::f64::consts::E),
+            ("(sin x)^2 + (cos x)^2", 1.0),
+            ("int_0^1 x^2 dx", 1.0 / 3.0),
+            ("max(1, pi) - pi + 3!", 6.0),
+            ("1 <= 2 and 3 != 4 or not 0", 1.0),
+            ("3 \"m\" + 2 \"m\"", 5.0),
+            ("[1 + 2) * 2", 6.0),
+        ] {
+            let eq = factory.parse(equation).unwrap();
+            if eq.variables().is_empty() {
+                assert_near!(expected, eq.evaluate().unwrap());
+            } else {
+                *eq.variable("x").unwrap() = 2.0;
+                assert_near!(expected, eq.evaluate().unwrap());
+            }
+        }
+
+        factory
+            .parse("sum_(i=1) i")
+            .expect_err("sum requires an upper bound");
+        factory
+            .parse("root 3")
+            .expect_err("root requires two arguments");
+        // the letters of a function name are not split into variables, e.g. s·i·n^2·x
+        let error = factory
+            .parse("sin^2 x")
+            .expect_err("a power may not be written on a function name");
+        assert_eq!(Some(ErrorCode::InvalidContext), error.code);
+        assert_eq!(Some(0..3), error.span);
+        assert!(error.help.is_some());
+    }
+
+    #[test]
+    fn test_spreadsheet_syntax() {
+        let factory = Parser::<f64>::new(Syntax::Spreadsheet).unwrap();
+        let grid = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]];
+
+        // ranges expand into a variable for each cell, which are set from a grid of rows
+        let eq = factory.parse("=SUM(A1:A3)*B2").unwrap();
+        assert_eq!(4, eq.variables().len());
+        eq.set_cells(&grid).unwrap();
+        assert_eq!(36.0, eq.evaluate().unwrap());
+
+        let eq = factory.parse("average($B$3:a1; 10) + A$1").unwrap();
+        assert_eq!(6, eq.variables().len());
+        eq.set_cells(&grid).unwrap();
+        assert_eq!(1.0 + 31.0 / 7.0, eq.evaluate().unwrap());
+
+        let eq = factory.parse("IF(A1>0,1,0) + POWER(2,3)").unwrap();
+        *eq.variable("A1").unwrap() = -1.0;
+        assert_eq!(8.0, eq.evaluate().unwrap());
+
+        for (equation, expected) in [
+            ("=-2^2", 4.0),
+            ("2^3^2", 64.0),
+            ("50%*10", 5.0),
+            ("PI() = PI()", 1.0),
+            ("AND(TRUE, 1 <> 2) + OR(FALSE(), 0)", 1.0),
+            ("MAX(MOD(7, 4), INT(2.5), LOG10(100))", 3.0),
+        ] {
+            assert_eq!(
+                expected,
+                factory.parse(equation).unwrap().evaluate().unwrap(),
+                "{equation}"
+            );
+        }
+
+        factory
+            .parse("SUM(A1:A3*2)")
+            .expect_err("a range must be a whole argument");
+        factory
+            .parse("A1:A3")
+            .expect_err("a range must be an argument of a function");
+        let eq = factory.parse("C1").unwrap();
+        assert!(matches!(
+            eq.set_cells(&grid).unwrap_err().error_type,
+            ErrorType::VariableAccessError
+        ));
+    }
+
+    #[test]
+    fn test_rpn_and_sexpr_syntax() {
+        let rpn = Parser::<f64>::new(Syntax::Rpn).unwrap();
+        let sexpr = Parser::<f64>::new(Syntax::SExpr).unwrap();
+
+        for (factory, equation, expected) in [
+            (&rpn, "3 4 + 2 *", 14.0),
+            (&rpn, "2 3 ^ neg 5 ! +", 112.0),
+            (&rpn, "3 -2 * 1 5 max -", -11.0),
+            (&rpn, "1 2 > 10 20 if", 20.0),
+            (&rpn, "i 1 4 i 2 ^ sum", 30.0),
+            (&sexpr, "(* (+ 3 4) 2)", 14.0),
+            (&sexpr, "(- 10 2 3)", 5.0),
+            (&sexpr, "(max 1 (- 5) (expt 2 3) (sqrt 4))", 8.0),
+            (&sexpr, "(if (/= 1 2) (not 0) 20)", 1.0),
+            (&sexpr, "(sum i 1 4 (* i i))", 30.0),
+        ] {
+            assert_eq!(
+                expected,
+                factory.parse(equation).unwrap().evaluate().unwrap(),
+                "{equation}"
+            );
+        }
+
+        let eq = rpn.parse("x 2 ^ y sin +").unwrap();
+        assert_eq!(2, eq.variables().len());
+        *eq.variable("x").unwrap() = 3.0;
+        let derivative = eq.derivative("x").unwrap();
+        *derivative.variable("x").unwrap() = 3.0;
+        assert_eq!(6.0, derivative.evaluate().unwrap());
+
+        let eq = sexpr.parse("(- x)").unwrap();
+        *eq.variable("x").unwrap() = 2.0;
+        assert_eq!(-2.0, eq.evaluate().unwrap());
+
+        // any ruleset may be used with another notation
+        let standard = Parser::<f64>::new(Syntax::Standard)
+            .unwrap()
+            .with_notation(Notation::Postfix);
+        assert_eq!(7.0, standard.parse("3 4 +").unwrap().evaluate().unwrap());
+
+        rpn.parse("3 4 - 2")
+            .expect_err("two values are left on the stack");
+        rpn.parse("+").expect_err("not enough arguments");
+        sexpr.parse("(* 2").expect_err("unclosed bracket");
+        sexpr
+            .parse("(+ 1 2) 3")
+            .expect_err("unexpected value after the expression");
+        sexpr.parse("(sqrt 4 5)").expect_err("too many arguments");
+        let error = sexpr.parse("()").expect_err("empty list");
+        assert_eq!(Some(ErrorCode::EmptyExpression), error.code);
+        assert_eq!(Some(0..2), error.span);
+
+        // nesting depth does not use up the call stack, even when it is small
+        let nested = std::thread::Builder::new()
+            .stack_size(512 * 1024)
+            .spawn(|| {
+                let depth = 500;
+                let nested = format!("{}1{}", "(+ 1 ".repeat(depth), ")".repeat(depth));
+                Parser::<f64>::new(Syntax::SExpr)
+                    .unwrap()
+                    .parse(&nested)
+                    .unwrap()
+                    .evaluate()
+                    .unwrap()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(501.0, nested);
+    }
+
+    pub fn triple<T: NumericType>(args: &[T]) -> Value<T> {
+        Ok(args[0] * T::from(3.0).unwrap())
+    }
+    static TRIPLE_FUNCTIONS: &[(&str, FunctionPointer<f64>, Arity)] =
+        &[("Triple", triple, Arity::Exactly(1))];
+
+    #[test]
+    fn test_mathml() {
+        for (markup, expected) in [
+            (
+                "<math><mfrac><mn>1</mn><mn>4</mn></mfrac></math>",
+                0.25,
+            ),
+            (
+                "<math><mn>2</mn><mo>+</mo><mn>3</mn><mo>&times;</mo><mn>4</mn></math>",
+                14.0,
+            ),
+            (
+                "<math><mrow><mo>&minus;</mo><msup><mn>2</mn><mn>2</mn></msup></mrow><mo>+</mo><msqrt><mn>9</mn></msqrt></math>",
+                -1.0,
+            ),
+            (
+                "<math><mn>2</mn><mo>(</mo><mn>1</mn><mo>+</mo><mn>2</mn><mo>)</mo><mo>!</mo></math>",
+                12.0,
+            ),
+            (
+                "<math><mroot><mn>27</mn><mn>3</mn></mroot><mo>&InvisibleTimes;</mo><mi>cos</mi><mo>&ApplyFunction;</mo><mn>0</mn></math>",
+                3.0,
+            ),
+            (
+                "<math><mi>max</mi><mo>(</mo><mn>1</mn><mo>,</mo><mn>5</mn><mo>,</mo><mn>2</mn><mo>)</mo></math>",
+                5.0,
+            ),
+            (
+                "<math><apply><plus/><cn>1</cn><cn>2</cn><apply><times/><cn>3</cn><cn>4</cn></apply></apply></math>",
+                15.0,
+            ),
+            (
+                "<math><apply><minus/><apply><power/><cn>2</cn><cn>3</cn></apply><apply><root/><degree><cn>3</cn></degree><cn>8</cn></apply></apply></math>",
+                6.0,
+            ),
+            (
+                "<math><apply><log/><logbase><cn>2</cn></logbase><cn>8</cn></apply></math>",
+                3.0,
+            ),
+        ] {
+            assert_near!(
+                expected,
+                whetstone::mathml::parse::<f64>(markup)
+                    .unwrap()
+                    .evaluate()
+                    .unwrap()
+            );
+        }
+
+        let eq = whetstone::mathml::parse::<f64>(
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML">
+                <mrow>
+                    <mn>2</mn><mo>&InvisibleTimes;</mo><mi>x</mi>
+                    <mo>+</mo>
+                    <mfrac><mi>y</mi><mn>2</mn></mfrac>
+                </mrow>
+            </math>"#,
+        )
+        .unwrap();
+        assert_eq!(2, eq.variables().len());
+        *eq.variable("x").unwrap() = 3.0;
+        *eq.variable("y").unwrap() = 4.0;
+        assert_eq!(8.0, eq.evaluate().unwrap());
+        assert_eq!(2.0, eq.derivative("x").unwrap().evaluate().unwrap());
+
+        let eq = whetstone::mathml::parse::<f64>(
+            "<math><apply><divide/><ci>a</ci><apply><minus/><ci>b</ci></apply></apply></math>",
+        )
+        .unwrap();
+        *eq.variable("a").unwrap() = 3.0;
+        *eq.variable("b").unwrap() = 2.0;
+        assert_eq!(-1.5, eq.evaluate().unwrap());
+
+        // functions named in markup use custom bindings with the same label
+        bindings::register_bindings(TRIPLE_FUNCTIONS).unwrap();
+        for markup in [
+            "<math><mi>Triple</mi><mo>&#x2061;</mo><mn>2</mn></math>",
+            "<math><apply><csymbol>Triple</csymbol><cn>2</cn></apply></math>",
+        ] {
+            let eq = whetstone::mathml::parse::<f64>(markup).unwrap();
+            assert_eq!(6.0, eq.evaluate().unwrap());
+        }
+
+        for markup in [
+            "<math><mn>1</mn><mo>+</mo></math>",
+            "<math><mfrac><mn>1</mn></mfrac></math>",
+            "<math><mn>1</mn>",
+            "<mrow><mn>1</mn></mrow>",
+            "<math><mi>NotABinding</mi><mo>&ApplyFunction;</mo><mn>1</mn></math>",
+            "<math><mtable/></math>",
+        ] {
+            whetstone::mathml::parse::<f64>(markup).expect_err(markup);
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        // repeated text is found at each of its own positions
+        let tokens = factory.tokenize(" x + x ").unwrap();
+        let spans: Vec<_> = tokens.iter().map(|token| token.span.clone()).collect();
+        assert_eq!(vec![1..2, 3..4, 5..6], spans);
+        assert_eq!(Some("Add".to_string()), tokens[1].binding);
+
+        // spans refer to the string as given, before full-width characters are replaced
+        let equation = "２ｘ＋sin(x)";
+        let tokens = factory.tokenize(equation).unwrap();
+        let categories: Vec<_> = tokens.iter().map(|token| token.category).collect();
+        assert_eq!(
+            vec![
+                Category::Literals,
+                Category::ImplicitOperators,
+                Category::Variables,
+                Category::Operators,
+                Category::Functions,
+                Category::OpenBrackets,
+                Category::Variables,
+                Category::CloseBrackets,
+            ],
+            categories
+        );
+        for token in &tokens {
+            assert_eq!(token.text, &equation[token.span.clone()]);
+        }
+        assert_eq!("", tokens[1].text);
+        assert_eq!(3..3, tokens[1].span);
+        assert_eq!(3..6, tokens[2].span);
+        assert_eq!(9..12, tokens[4].span);
+        assert_eq!(Some("Sine".to_string()), tokens[4].binding);
+
+        factory.tokenize("x +").unwrap_err();
+        factory.tokenize("sin(x").unwrap_err();
+
+        // prefixes and separators of argument groups are tokens of their own
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        let texts: Vec<_> = factory
+            .tokenize("\\sum_{i=1}^{n} i")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.text)
+            .collect();
+        assert_eq!(
+            vec!["\\sum", "_", "{", "i", "=", "1", "}", "^", "{", "n", "}", "i"],
+            texts
+        );
+
+        let tokens = Parser::<f64>::new(Syntax::SExpr)
+            .unwrap()
+            .tokenize("(+ 1 (* 2 3))")
+            .unwrap();
+        assert_eq!(9, tokens.len());
+        assert_eq!(Some("Multiply".to_string()), tokens[4].binding);
+        assert_eq!(8..9, tokens[5].span);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        for (equation, code, span) in [
+            ("2 * (3 + 4", ErrorCode::UnmatchedBracket, 4..5),
+            ("(2])", ErrorCode::UnmatchedBracket, 2..3),
+            ("2 + # 3", ErrorCode::UnknownToken, 4..5),
+            ("2 + * 3", ErrorCode::InvalidContext, 4..5),
+            ("2 +", ErrorCode::UnexpectedEnd, 2..3),
+            ("sqrt(1, 2)", ErrorCode::ArityMismatch, 0..4),
+            ("3 , 4", ErrorCode::ArityMismatch, 0..5),
+            ("   ", ErrorCode::EmptyExpression, 0..0),
+        ] {
+            let error = factory.parse(equation).unwrap_err();
+            assert_eq!(Some(code), error.code, "{equation}");
+            if code != ErrorCode::EmptyExpression {
+                assert_eq!(Some(span), error.span, "{equation}");
+            }
+        }
+
+        // spans refer to the string as given, before full-width characters are replaced
+        let error = factory.parse("２＋＃").unwrap_err();
+        assert_eq!(Some(6..9), error.span);
+        // messages leave the location to the span, which is in the program as given
+        let error = factory.parse_program("x = 1;  y = x # 2; y").unwrap_err();
+        assert_eq!(Some(14..15), error.span);
+        assert!(!error.message.contains("position"));
+
+        let error = factory.parse("2 * (3 + 4").unwrap_err();
+        assert_eq!(
+            "error[unmatched-bracket]: Unclosed opening bracket\n  |\n1 | 2 * (3 + 4\n  |     ^\n  = help: add a closing bracket after the bracketed values\n",
+            error.render("2 * (3 + 4")
+        );
+        assert_eq!("Unclosed opening bracket", error.to_string());
+        let error: Box<dyn std::error::Error> = Box::new(error);
+        assert_eq!("Unclosed opening bracket", error.to_string());
+
+        // errors in a program are underlined on the line of the statement
+        let program = "a = 1;\nb = a + ;\nb";
+        let error = factory.parse_program(program).unwrap_err();
+        assert_eq!(Some(ErrorCode::UnexpectedEnd), error.code);
+        assert_eq!(Some(13..14), error.span);
+        assert!(error
+            .render(program)
+            .contains("2 | b = a + ;\n  |       ^\n"));
+
+        let error = factory.parse_program("f(x) = x; f(y) = 2").unwrap_err();
+        assert_eq!(Some(ErrorCode::InvalidAssignment), error.code);
+        assert_eq!(Some(10..16), error.span);
+
+        let error = Parser::<f64>::new(Syntax::LaTeX)
+            .unwrap()
+            .parse("\\frac{1}")
+            .unwrap_err();
+        assert_eq!(Some(ErrorCode::MissingArgumentGroup), error.code);
+        assert_eq!(Some(0..5), error.span);
+
+        let mut definitions = syntax::get_definitions(Syntax::Standard).unwrap();
+        definitions
+            .get_mut(&Category::Functions)
+            .unwrap()
+            .rules
+            .push(RuleDefinition {
+                pattern: Some("nosuch".to_string()),
+                binding: Some("NoSuchBinding".to_string()),
+                ..Default::default()
+            });
+        let error = match Parser::<f64>::from_definitions(definitions) {
+            Ok(_) => panic!("Unknown binding not detected"),
+            Err(error) => error,
+        };
+        assert_eq!(Some(ErrorCode::UnknownBinding), error.code);
+        assert!(error.help.is_some());
+        assert_eq!(None, error.span);
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        // parsing carries on after each error, keeping the rest of the equation where possible
+        for (equation, expected, codes) in [
+            ("2 + # 3", Some(5.0), vec![ErrorCode::UnknownToken]),
+            (
+                "2 + # 3 * $ 4",
+                Some(14.0),
+                vec![ErrorCode::UnknownToken, ErrorCode::UnknownToken],
+            ),
+            ("2 $ + 3", Some(5.0), vec![ErrorCode::UnknownToken]),
+            (
+                "2 # 3 # 4",
+                Some(2.0),
+                vec![ErrorCode::UnknownToken, ErrorCode::UnknownToken],
+            ),
+            ("2 ## 3", Some(2.0), vec![ErrorCode::UnknownToken]),
+            (
+                "2 + * 3 + 4 +",
+                Some(9.0),
+                vec![ErrorCode::InvalidContext, ErrorCode::UnexpectedEnd],
+            ),
+            ("2 + 3)", Some(5.0), vec![ErrorCode::UnmatchedBracket]),
+            ("(1 + #) * 2", Some(2.0), vec![ErrorCode::UnknownToken]),
+            ("max(1, #, 3)", Some(3.0), vec![ErrorCode::UnknownToken]),
+            (
+                "sqrt(9 + 0 * #",
+                Some(3.0),
+                vec![ErrorCode::UnmatchedBracket, ErrorCode::UnknownToken],
+            ),
+            (
+                "((1",
+                Some(1.0),
+                vec![ErrorCode::UnmatchedBracket, ErrorCode::UnmatchedBracket],
+            ),
+            (
+                "sqrt(1, 2) + #",
+                None,
+                vec![ErrorCode::ArityMismatch, ErrorCode::UnknownToken],
+            ),
+            ("#", None, vec![ErrorCode::UnknownToken]),
+            ("1 + 2", Some(3.0), vec![]),
+        ] {
+            let report = factory.parse_recovering(equation);
+            let found: Vec<_> = report
+                .errors
+                .iter()
+                .filter_map(|error| error.code)
+                .collect();
+            assert_eq!(codes, found, "{equation}");
+            assert_eq!(codes.is_empty(), report.is_ok(), "{equation}");
+            assert_eq!(
+                expected,
+                report.equation.map(|eq| eq.evaluate().unwrap()),
+                "{equation}"
+            );
+        }
+
+        // errors are in the order of where they are, with spans in the string as given
+        let report = factory.parse_recovering("sin(x + ＃ * 2");
+        let spans: Vec<_> = report
+            .errors
+            .iter()
+            .map(|error| error.span.clone().unwrap())
+            .collect();
+        assert_eq!(vec![3..4, 8..11], spans);
+
+        // the error with a recovered partial equation is the same as the first error of parse
+        let error = factory.parse("2 + # 3 * $ 4").unwrap_err();
+        let report = factory.parse_recovering("2 + # 3 * $ 4");
+        assert_eq!(error.message, report.errors[0].message);
+        assert_eq!(error.span, report.errors[0].span);
+
+        let report = Parser::<f64>::new(Syntax::LaTeX)
+            .unwrap()
+            .parse_recovering("\\frac{1}{2 #} + \\sqrt{16");
+        assert_eq!(2, report.errors.len());
+        assert_eq!(4.5, report.equation.unwrap().evaluate().unwrap());
+
+        // other notations stop at the first error
+        let report = Parser::<f64>::new(Syntax::Rpn)
+            .unwrap()
+            .parse_recovering("1 # 2 $ +");
+        assert_eq!(1, report.errors.len());
+        assert!(report.equation.is_none());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        // text no rule matches is compared against the text of rules
+        let factory = Parser::<f64>::new(Syntax::Spreadsheet).unwrap();
+        let error = factory.parse("=AVERAG(A1, 2)").unwrap_err();
+        assert_eq!(Some(ErrorCode::UnknownToken), error.code);
+        assert_eq!(vec!["AVERAGE"], error.suggestions);
+        assert!(error
+            .render("=AVERAG(A1, 2)")
+            .ends_with("  = help: did you mean 'AVERAGE'?\n"));
+        // the whole word is compared, even where the start of it matches a rule
+        let error = factory.parse("=1 + SUMM(A1)").unwrap_err();
+        assert_eq!(Some(8..9), error.span);
+        assert_eq!(vec!["SUM"], error.suggestions);
+        // symbols are not compared, as every symbol is one edit from another
+        let error = factory.parse("=1 # 2").unwrap_err();
+        assert!(error.suggestions.is_empty());
+
+        // variables named almost the same as a function are warned about
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+        let report = factory.parse_recovering("sinn(x) + sqr(2) + abs(xy)");
+        assert!(report.is_ok());
+        assert!(report.equation.is_some());
+        assert_eq!(2, report.warnings.len());
+        let warning = &report.warnings[0];
+        assert!(matches!(warning.error_type, ErrorType::Warning));
+        assert_eq!(Some(ErrorCode::MisspeltFunction), warning.code);
+        assert_eq!(Some(0..4), warning.span);
+        assert_eq!("sin", warning.suggestions[0]);
+        assert_eq!(Some(10..13), report.warnings[1].span);
+        assert_eq!(vec!["sqrt"], report.warnings[1].suggestions);
+        assert!(report.warnings[1]
+            .render("sinn(x) + sqr(2) + abs(xy)")
+            .starts_with("warning[misspelt-function]: "));
+
+        // short variables are not compared
+        assert!(factory.parse_recovering("xn + ln(x)").warnings.is_empty());
+
+        let report = Parser::<f64>::new(Syntax::LaTeX)
+            .unwrap()
+            .parse_recovering("\\frca{1}{2}");
+        assert_eq!(vec!["\\frac"], report.warnings[0].suggestions);
+    }
+
+    #[test]
+    fn test_lenient_parse() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+        let report = factory.parse_lenient("max(1, (2 + 3 *");
+        assert!(report.is_ok());
+        assert_eq!(5.0, report.equation.unwrap().evaluate().unwrap());
+        assert_eq!(
+            vec![
+                Repair::DroppedToken {
+                    text: "*".to_string(),
+                    span: 14..15
+                },
+                Repair::ClosedBracket {
+                    text: ")".to_string(),
+                    opening: 7..8
+                },
+                Repair::ClosedBracket {
+                    text: ")".to_string(),
+                    opening: 3..4
+                },
+            ],
+            report.repairs
+        );
+
+        // tokens are left out until the end may be parsed
+        let report = factory.parse_lenient("2 * (");
+        assert_eq!(2.0, report.equation.unwrap().evaluate().unwrap());
+        assert_eq!(2, report.repairs.len());
+        let report = factory.parse_lenient("max(1,");
+        assert_eq!(1.0, report.equation.unwrap().evaluate().unwrap());
+        // a complete equation needs no repairs
+        let report = factory.parse_lenient("|-3|");
+        assert!(report.repairs.is_empty());
+        assert_eq!(3.0, report.equation.unwrap().evaluate().unwrap());
+
+        // the closing bracket added is that of the opening bracket's context
+        let report = Parser::<f64>::new(Syntax::LaTeX)
+            .unwrap()
+            .parse_lenient("\\left(1 + \\sqrt{16");
+        assert_eq!(5.0, report.equation.unwrap().evaluate().unwrap());
+        let closed: Vec<_> = report
+            .repairs
+            .iter()
+            .map(|repair| match repair {
+                Repair::ClosedBracket { text, .. } => text.as_str(),
+                Repair::DroppedToken { text, .. } => text.as_str(),
+            })
+            .collect();
+        assert_eq!(vec!["}", "\\right)"], closed);
+        let report = Parser::<f64>::new(Syntax::SExpr)
+            .unwrap()
+            .parse_lenient("(+ 1 (* 2 3");
+        assert_eq!(7.0, report.equation.unwrap().evaluate().unwrap());
+
+        // errors before the end are not repaired
+        for equation in ["2 + # +", "2 + 3)", "sin("] {
+            let report = factory.parse_lenient(equation);
+            assert!(report.equation.is_none());
+            assert_eq!(1, report.errors.len());
+        }
+        let report = factory.parse_lenient("2 + 3)");
+        assert_eq!(Some(ErrorCode::UnmatchedBracket), report.errors[0].code);
+    }
+
     #[test]
     fn test_order_of_operations() {
         let factory = Parser::<f32>::new(Syntax::Standard).unwrap();
@@ -212,114 +902,517 @@ mod tests {
     }
 
     #[test]
-    fn test_bad_syntax() {
+    fn test_wrapping_functions() {
         let factory = Parser::<f32>::new(Syntax::Standard).unwrap();
 
+        let eq = factory.parse("|x - 3| + 1").unwrap();
+        *eq.variable("x").unwrap() = 1.0;
+        assert_eq!(3.0, eq.evaluate().unwrap());
+
+        assert_eq!(6.0, factory.parse("2|-3|").unwrap().evaluate().unwrap());
+        assert_eq!(5.0, factory.parse("|-2||3|-1").unwrap().evaluate().unwrap());
+        assert_eq!(
+            4.0,
+            factory.parse("||-4| - 8|").unwrap().evaluate().unwrap()
+        );
+        assert_near!(
+            1.0f32.sin(),
+            factory.parse("sin|-1|").unwrap().evaluate().unwrap()
+        );
+
         factory
-            .parse("")
-            .expect_err("Should return error for empty equation");
-        factory
-            .parse("+")
-            .expect_err("Should return error for invalid operator usage");
-        factory
-            .parse("2+-")
-            .expect_err("Should return error for invalid operator usage");
+            .parse("|2")
+            .expect_err("Unclosed wrapping bracket not detected");
         factory
-            .parse("sin")
-            .expect_err("Should return error for argumentless function");
+            .parse("(2|")
+            .expect_err("Mismatched wrapping bracket not detected");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+
+        let eq = factory
+            .parse(
+                "\\lfloor x \\rfloor + \\lceil x \\rceil + \\left| -x \\right| + \\lvert x \\rvert",
+            )
+            .unwrap();
+        *eq.variable("x").unwrap() = 1.5;
+        assert_eq!(6.0, eq.evaluate().unwrap());
+
         factory
-            .parse("2 2 2")
-            .expect_err("Should return error for meaningless drivel");
+            .parse("\\lfloor x \\rceil")
+            .expect_err("Mismatched wrapping bracket not detected");
     }
 
     #[test]
-    fn test_modify_syntax() {
-        let mut modified_standard: syntax::RuleCollectionDefinition =
-            syntax::get_definitions(Syntax::Standard).unwrap();
-        let var_rule = &mut modified_standard
-            .get_mut(&Category::Variables)
-            .unwrap()
-            .rules[0];
-        var_rule.pattern = Some("x".to_string());
-        var_rule.pattern_is_regex = Some(false);
+    fn test_capturing_functions() {
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
 
-        let factory = Parser::<f32>::from_definitions(modified_standard).unwrap();
+        let eq = factory.parse("\\frac{1}{x}").unwrap();
+        *eq.variable("x").unwrap() = 4.0;
+        assert_eq!(0.25, eq.evaluate().unwrap());
 
-        let eq = factory.parse("x+1").unwrap();
-        assert_eq!(1.0, eq.evaluate().unwrap());
+        let eq = factory
+            .parse("\\dfrac{x + 1}{\\tfrac{x}{2}} \\cdot \\cfrac{1}{2}")
+            .unwrap();
+        *eq.variable("x").unwrap() = 4.0;
+        assert_eq!(1.25, eq.evaluate().unwrap());
 
-        factory
-            .parse("y+1")
-            .expect_err("y should not be considered a valid variable in our modified syntax");
-    }
+        let eq = factory.parse("\\sqrt[3]{x} + \\sqrt{x}").unwrap();
+        *eq.variable("x").unwrap() = 64.0;
+        assert_near!(12.0, eq.evaluate().unwrap());
 
-    #[test]
-    fn test_new_syntax() {
-        const JSON: &str = "{
-                \"Operators\": {
-                    \"may_follow\": [\"Literals\", \"Variables\"],
-                    \"rules\": [
-                        {
-                            \"pattern\": \"+\",
-                            \"binding\": \"Add\",
-                            \"precedence\": 1
-                        }
-                    ]
-                },
-                \"Literals\": {
-                    \"may_follow\": [\"Operators\"],
-                    \"rules\": [
-                        {
-                            \"pattern\": \"[0-9]+\",
-                            \"pattern_is_regex\": true
-                        }
-                    ]
-                },
-                \"Variables\": {
-                    \"may_follow\": [\"Operators\"],
-                    \"rules\": [
-                        {
-                            \"pattern\": \"x\"
-                        }
-                    ]
-                }
-            }";
-        println!("{}", JSON);
-        let factory = Parser::<f32>::from_json(JSON).unwrap();
+        let eq = factory.parse("\\log_{2}{x} - \\log_{10}{100}").unwrap();
+        *eq.variable("x").unwrap() = 32.0;
+        assert_near!(3.0, eq.evaluate().unwrap());
 
-        let eq = factory.parse("2 + x").unwrap();
-        *eq.variable("x").unwrap() = 3.0;
-        assert_eq!(5.0, eq.evaluate().unwrap());
+        assert_near!(
+            4.0,
+            factory
+                .parse("2\\frac{\\sqrt{16}}{2}")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
 
-        factory.parse("y").expect_err("y is not a valid variable");
-        factory.parse("2*3").expect_err("* operator not defined");
-        factory
-            .parse("2.0")
-            .expect_err("only integer literals defined");
-    }
+        // a single token may be given without braces
+        assert_eq!(2.0, factory.parse("\\sqrt 4").unwrap().evaluate().unwrap());
+        assert_eq!(
+            5.0,
+            factory.parse("2\\sqrt 4 + 1").unwrap().evaluate().unwrap()
+        );
+        assert_eq!(
+            2.0,
+            factory.parse("\\sqrt[3] 8").unwrap().evaluate().unwrap()
+        );
+        assert_near!(
+            3.0,
+            factory.parse("\\log_2{8}").unwrap().evaluate().unwrap()
+        );
+        assert_near!(3.0, factory.parse("\\log_2 8").unwrap().evaluate().unwrap());
 
-    pub fn custom_func<T: NumericType>(args: &[T]) -> Value<T> {
-        Ok(args[0] + (T::from(1.0).unwrap() / args[0]))
+        factory
+            .parse("\\frac{1}")
+            .expect_err("Missing argument group not detected");
+        factory
+            .parse("\\frac{1} 2")
+            .expect_err("Missing argument group not detected");
+        factory
+            .parse("\\frac 1 2")
+            .expect_err("Missing argument group not detected");
+        factory
+            .parse("\\sqrt[3]")
+            .expect_err("Missing argument group not detected");
     }
-    static MY_FUNCTIONS: &[(&str, FunctionPointer<f32>, usize)] = &[("CustomFunc", custom_func, 1)];
 
     #[test]
-    fn test_add_bindings() {
-        // register bindings defined above with whetstone
-        let _ = bindings::register_bindings(MY_FUNCTIONS).unwrap();
+    fn test_variadic_functions() {
+        let factory = Parser::<f32>::new(Syntax::Standard).unwrap();
 
-        // modify ruleset to add a function using this binding
-        let mut modified_standard: syntax::RuleCollectionDefinition =
-            syntax::get_definitions(Syntax::Standard).unwrap();
-        let func_rules = &mut modified_standard.get_mut(&Category::Functions).unwrap();
-        func_rules.rules.push(RuleDefinition {
-            pattern: Some("self_plus_half_self".to_string()),
-            pattern_is_regex: Some(false),
+        let eq = factory.parse("max(a, b, c) - min(a, b, c, 4)").unwrap();
+        *eq.variable("a").unwrap() = 3.0;
+        *eq.variable("b").unwrap() = 9.0;
+        *eq.variable("c").unwrap() = 1.0;
+        assert_eq!(8.0, eq.evaluate().unwrap());
+
+        assert_eq!(
+            2.5,
+            factory
+                .parse("mean(1, 2, 3, 4)")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        // sum takes an index variable, so a list of values is summed by total
+        assert_eq!(
+            6.0,
+            factory.parse("total(1, 2, 3)").unwrap().evaluate().unwrap()
+        );
+        factory
+            .parse("sum(1, 2, 3)")
+            .expect_err("sum requires an index variable");
+        assert_eq!(5.0, factory.parse("max(5)").unwrap().evaluate().unwrap());
+        assert_eq!(
+            7.0,
+            factory
+                .parse("max(min(7, 8), mean(2, 4))")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+
+        factory
+            .parse("sin(1, 2)")
+            .expect_err("Too many arguments not detected");
+        factory
+            .parse("mod(1)")
+            .expect_err("Too few arguments not detected");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        assert_eq!(
+            3.0,
+            factory
+                .parse("\\max\\{1, 2, 3\\}")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_postfix_operators() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        assert_eq!(120.0, factory.parse("5!").unwrap().evaluate().unwrap());
+        assert_eq!(1.0, factory.parse("0!").unwrap().evaluate().unwrap());
+        assert_near!(
+            std::f64::consts::PI.sqrt() / 2.0,
+            factory.parse("0.5!").unwrap().evaluate().unwrap()
+        );
+        assert_near!(
+            -std::f64::consts::PI.sqrt() * 2.0,
+            factory.parse("(-1.5)!").unwrap().evaluate().unwrap()
+        );
+        // the factorial of a negative integer is undefined
+        assert!(factory.parse("(-3)!").unwrap().evaluate().unwrap().is_nan());
+        assert!(factory.parse("(-1)!").unwrap().evaluate().unwrap().is_nan());
+        assert_eq!(64.0, factory.parse("2^3!").unwrap().evaluate().unwrap());
+        assert_eq!(720.0, factory.parse("3!!").unwrap().evaluate().unwrap());
+        assert_eq!(12.0, factory.parse("2(3!)").unwrap().evaluate().unwrap());
+        let eq = factory.parse("3!x").unwrap();
+        *eq.variable("x").unwrap() = 2.0;
+        assert_eq!(12.0, eq.evaluate().unwrap());
+
+        let eq = factory.parse("x% * 200 + 7 % 4").unwrap();
+        *eq.variable("x").unwrap() = 50.0;
+        assert_eq!(103.0, eq.evaluate().unwrap());
+        assert_eq!(0.5, factory.parse("50%").unwrap().evaluate().unwrap());
+        // % followed by a sign is a percent, as modulo does not take a signed operand
+        assert_eq!(1.5, factory.parse("50%+1").unwrap().evaluate().unwrap());
+        let eq = factory.parse("50%-x").unwrap();
+        *eq.variable("x").unwrap() = 2.0;
+        assert_eq!(-1.5, eq.evaluate().unwrap());
+        assert_eq!(1.0, factory.parse("7 % (-3)").unwrap().evaluate().unwrap());
+        assert_eq!(
+            1.5,
+            factory
+                .parse("max(50%, 1) + 50%")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+
+        factory
+            .parse("!3")
+            .expect_err("Postfix operator may not start an expression");
+        factory
+            .parse("3 + !")
+            .expect_err("Postfix operator must follow a value");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        assert_eq!(
+            6.5,
+            factory.parse("3! + 50\\%").unwrap().evaluate().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_prefix_operators() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        assert_eq!(-4.0, factory.parse("-2^2").unwrap().evaluate().unwrap());
+        assert_eq!(-4.0, factory.parse("-(2)^2").unwrap().evaluate().unwrap());
+        assert_eq!(0.25, factory.parse("2^-2").unwrap().evaluate().unwrap());
+        assert_eq!(-6.0, factory.parse("2*-3").unwrap().evaluate().unwrap());
+        assert_eq!(-6.0, factory.parse("-3!").unwrap().evaluate().unwrap());
+        assert_eq!(3.0, factory.parse("--3").unwrap().evaluate().unwrap());
+        assert_eq!(3.0, factory.parse("+3").unwrap().evaluate().unwrap());
+        assert_eq!(
+            -1.0,
+            factory.parse("-sin(pi/2)").unwrap().evaluate().unwrap()
+        );
+
+        let eq = factory.parse("-x^2 + -2x").unwrap();
+        *eq.variable("x").unwrap() = 3.0;
+        assert_eq!(-15.0, eq.evaluate().unwrap());
+
+        // the function applies to the prefix operator and its operand only
+        assert_near!(
+            (-2.0f64).sin() + 1.0,
+            factory.parse("sin -2 + 1").unwrap().evaluate().unwrap()
+        );
+        assert_near!(
+            (-4.0f64).sin() * 3.0,
+            factory.parse("sin -2^2 * 3").unwrap().evaluate().unwrap()
+        );
+
+        let eq = factory.parse("not x + not 0").unwrap();
+        *eq.variable("x").unwrap() = 3.0;
+        assert_eq!(1.0, eq.evaluate().unwrap());
+
+        factory
+            .parse("2-")
+            .expect_err("Prefix operator may not end an expression");
+        factory
+            .parse("-")
+            .expect_err("Prefix operator may not end an expression");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        assert_eq!(-9.0, factory.parse("-3^2").unwrap().evaluate().unwrap());
+        assert_near!(
+            (-2.0f64).sin() + 1.0,
+            factory.parse("\\sin -2 + 1").unwrap().evaluate().unwrap()
+        );
+        assert_eq!(
+            1.0,
+            factory
+                .parse("\\neg 0 + \\lnot 2")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bad_syntax() {
+        let factory = Parser::<f32>::new(Syntax::Standard).unwrap();
+
+        factory
+            .parse("")
+            .expect_err("Should return error for empty equation");
+        factory
+            .parse("+")
+            .expect_err("Should return error for invalid operator usage");
+        factory
+            .parse("2+-")
+            .expect_err("Should return error for invalid operator usage");
+        factory
+            .parse("sin")
+            .expect_err("Should return error for argumentless function");
+        factory
+            .parse("2 2 2")
+            .expect_err("Should return error for meaningless drivel");
+    }
+
+    #[test]
+    fn test_modify_syntax() {
+        let mut modified_standard: syntax::RuleCollectionDefinition =
+            syntax::get_definitions(Syntax::Standard).unwrap();
+        let var_rule = &mut modified_standard
+            .get_mut(&Category::Variables)
+            .unwrap()
+            .rules[0];
+        var_rule.pattern = Some("x".to_string());
+        var_rule.pattern_is_regex = Some(false);
+
+        let factory = Parser::<f32>::from_definitions(modified_standard).unwrap();
+
+        let eq = factory.parse("x+1").unwrap();
+        assert_eq!(1.0, eq.evaluate().unwrap());
+
+        factory
+            .parse("y+1")
+            .expect_err("y should not be considered a valid variable in our modified syntax");
+    }
+
+    #[test]
+    fn test_new_syntax() {
+        const JSON: &str = "{
+                \"Operators\": {
+                    \"may_follow\": [\"Literals\", \"Variables\"],
+                    \"rules\": [
+                        {
+                            \"pattern\": \"+\",
+                            \"binding\": \"Add\",
+                            \"precedence\": 1
+                        }
+                    ]
+                },
+                \"Literals\": {
+                    \"may_follow\": [\"Operators\"],
+                    \"rules\": [
+                        {
+                            \"pattern\": \"[0-9]+\",
+                            \"pattern_is_regex\": true
+                        }
+                    ]
+                },
+                \"Variables\": {
+                    \"may_follow\": [\"Operators\"],
+                    \"rules\": [
+                        {
+                            \"pattern\": \"x\"
+                        }
+                    ]
+                }
+            }";
+        println!("{}", JSON);
+        let factory = Parser::<f32>::from_json(JSON).unwrap();
+
+        let eq = factory.parse("2 + x").unwrap();
+        *eq.variable("x").unwrap() = 3.0;
+        assert_eq!(5.0, eq.evaluate().unwrap());
+
+        factory.parse("y").expect_err("y is not a valid variable");
+        factory.parse("2*3").expect_err("* operator not defined");
+        factory
+            .parse("2.0")
+            .expect_err("only integer literals defined");
+    }
+
+    #[test]
+    fn test_case_sensitivity() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        // constants such as e match case exactly, so E is a variable
+        let eq = factory.parse("E + e").unwrap();
+        assert_eq!(vec!["E".to_string()], eq.variables());
+        *eq.variable("E").unwrap() = 1.0;
+        assert_near!(1.0 + std::f64::consts::E, eq.evaluate().unwrap());
+
+        // function names, word operators and literal prefixes do not
+        for (equation, expected) in [
+            ("SIN(0) + Cos(0)", 1.0),
+            ("PI / pi", 1.0),
+            ("0xFF - 0XFF", 0.0),
+            ("TRUE AND NOT false", 1.0),
+        ] {
+            assert_eq!(
+                expected,
+                factory.parse(equation).unwrap().evaluate().unwrap(),
+                "{equation}"
+            );
+        }
+
+        // functions defined by a program are named like variables
+        let program = factory.parse_program("f(x) = 2x; f(3) + F").unwrap();
+        *program.variable("F").unwrap() = 1.0;
+        assert_eq!(7.0, program.evaluate().unwrap());
+
+        // LaTeX commands differ by case, so \Pi is a variable rather than the constant
+        let latex = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        assert_eq!(
+            1,
+            latex.parse("\\Pi \\cdot \\pi").unwrap().variables().len()
+        );
+
+        // spreadsheet formulas ignore case
+        let spreadsheet = Parser::<f64>::new(Syntax::Spreadsheet).unwrap();
+        let eq = spreadsheet.parse("=sum(a1, B1)").unwrap();
+        eq.set_cells(&[vec![1.0, 2.0]]).unwrap();
+        assert_eq!(3.0, eq.evaluate().unwrap());
+
+        // a rule overrides its category, which overrides the collection
+        const JSON: &str = r#"{
+                "case_sensitive": true,
+                "Functions": {
+                    "may_follow": ["Operators"],
+                    "case_sensitive": false,
+                    "rules": [
+                        { "pattern": "sqrt", "binding": "SquareRoot" },
+                        { "pattern": "Abs", "binding": "Absolute", "case_sensitive": true }
+                    ]
+                },
+                "Operators": {
+                    "may_follow": ["Literals", "CloseBrackets"],
+                    "rules": [{ "pattern": "plus", "binding": "Add", "precedence": 1 }]
+                },
+                "Literals": {
+                    "may_follow": ["Operators", "OpenBrackets"],
+                    "rules": [{ "pattern": "[0-9]+", "pattern_is_regex": true }]
+                },
+                "OpenBrackets": {
+                    "may_follow": ["Operators", "Functions"],
+                    "rules": [{ "pattern": "(", "context": 0 }]
+                },
+                "CloseBrackets": {
+                    "may_follow": ["Literals", "CloseBrackets"],
+                    "rules": [{ "pattern": ")", "context": 0 }]
+                }
+            }"#;
+        let factory = Parser::<f64>::from_json(JSON).unwrap();
+        assert_eq!(
+            5.0,
+            factory
+                .parse("SQRT(4) plus Abs(3)")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        factory.parse("1 PLUS 2").expect_err("operators match case");
+        factory.parse("abs(3)").expect_err("Abs matches case");
+    }
+
+    #[test]
+    fn test_single_letter_variables() {
+        let mut definitions = syntax::get_definitions(Syntax::Standard).unwrap();
+        definitions
+            .get_mut(&Category::Variables)
+            .unwrap()
+            .variable_mode = Some(VariableMode::SingleLetter);
+        let factory = Parser::<f64>::from_definitions(definitions).unwrap();
+
+        let eq = factory.parse("2ab + xy").unwrap();
+        assert_eq!(4, eq.variables().len());
+        for (label, value) in [("a", 2.0), ("b", 3.0), ("x", 4.0), ("y", 5.0)] {
+            *eq.variable(label).unwrap() = value;
+        }
+        assert_eq!(32.0, eq.evaluate().unwrap());
+
+        // digits after a letter are its subscript
+        let eq = factory.parse("x1y2 - x1").unwrap();
+        let mut variables = eq.variables().to_vec();
+        variables.sort();
+        assert_eq!(vec!["x1", "y2"], variables);
+
+        // function names and constants are still matched whole
+        let eq = factory.parse("sin x + pi r^2 + ex").unwrap();
+        let mut variables = eq.variables().to_vec();
+        variables.sort();
+        assert_eq!(vec!["r", "x"], variables);
+        *eq.variable("x").unwrap() = 0.0;
+        *eq.variable("r").unwrap() = 1.0;
+        assert_near!(std::f64::consts::PI, eq.evaluate().unwrap());
+
+        let eq = factory.parse("max(ab, cos(0))").unwrap();
+        *eq.variable("a").unwrap() = 2.0;
+        *eq.variable("b").unwrap() = 3.0;
+        assert_eq!(6.0, eq.evaluate().unwrap());
+
+        // the default mode reads a run of letters as one variable
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+        assert_eq!(vec!["ab"], factory.parse("ab").unwrap().variables());
+
+        let mut definitions = syntax::get_definitions(Syntax::Standard).unwrap();
+        definitions
+            .get_mut(&Category::Constants)
+            .unwrap()
+            .variable_mode = Some(VariableMode::SingleLetter);
+        assert!(Parser::<f64>::from_definitions(definitions).is_err());
+    }
+
+    pub fn custom_func<T: NumericType>(args: &[T]) -> Value<T> {
+        Ok(args[0] + (T::from(1.0).unwrap() / args[0]))
+    }
+    static MY_FUNCTIONS: &[(&str, FunctionPointer<f32>, Arity)] =
+        &[("CustomFunc", custom_func, Arity::Exactly(1))];
+
+    #[test]
+    fn test_add_bindings() {
+        // register bindings defined above with whetstone
+        bindings::register_bindings(MY_FUNCTIONS).unwrap();
+
+        // modify ruleset to add a function using this binding
+        let mut modified_standard: syntax::RuleCollectionDefinition =
+            syntax::get_definitions(Syntax::Standard).unwrap();
+        let func_rules = &mut modified_standard.get_mut(&Category::Functions).unwrap();
+        func_rules.rules.push(RuleDefinition {
+            pattern: Some("self_plus_half_self".to_string()),
+            pattern_is_regex: Some(false),
             precedence: Some(1),
             associativity: Some(syntax::Associativity::LeftToRight),
             binding: Some("CustomFunc".to_string()),
             may_follow: None,
             context: None,
+            ..Default::default()
         });
 
         // create parser using modified ruleset
@@ -329,4 +1422,657 @@ mod tests {
         let eq = factory.parse("self_plus_half_self(2)").unwrap();
         assert_eq!(2.5, eq.evaluate().unwrap());
     }
+
+    pub fn cube<T: NumericType>(args: &[T]) -> Value<T> {
+        Ok(args[0] * args[0] * args[0])
+    }
+    pub fn cube_derivative(args: &[Term<f64>]) -> Result<Vec<Term<f64>>, Error> {
+        Ok(vec![
+            Term::number(3.0) * args[0].clone().pow(Term::number(2.0)),
+        ])
+    }
+    static CUBE_FUNCTIONS: &[(&str, FunctionPointer<f64>, Arity)] =
+        &[("Cube", cube, Arity::Exactly(1))];
+    static CUBE_DERIVATIVES: &[(&str, DerivativePointer<f64>)] = &[("Cube", cube_derivative)];
+
+    #[test]
+    fn test_derivatives() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        let eq = factory.parse("x^3 + 2x").unwrap();
+        *eq.variable("x").unwrap() = 2.0;
+        let derivative = eq.derivative("x").unwrap();
+        assert_near!(14.0, derivative.evaluate().unwrap());
+        // derivative has its own variables, starting with the same values
+        *derivative.variable("x").unwrap() = 1.0;
+        assert_near!(5.0, derivative.evaluate().unwrap());
+        assert_near!(12.0, eq.evaluate().unwrap());
+        let second = derivative.derivative("x").unwrap();
+        assert_near!(6.0, second.evaluate().unwrap());
+
+        // other variables are treated as constants but are still listed
+        let eq = factory.parse("x*y + sin(y)").unwrap();
+        let derivative = eq.derivative("x").unwrap();
+        assert_eq!(2, derivative.variables().len());
+        *derivative.variable("y").unwrap() = 3.0;
+        assert_near!(3.0, derivative.evaluate().unwrap());
+        let derivative = eq.derivative("y").unwrap();
+        *derivative.variable("x").unwrap() = 2.0;
+        *derivative.variable("y").unwrap() = 0.0;
+        assert_near!(3.0, derivative.evaluate().unwrap());
+        assert_near!(0.0, eq.derivative("z").unwrap().evaluate().unwrap());
+
+        // chain rule and built-in rules
+        let cases: &[(&str, f64, f64)] = &[
+            ("sin(x^2)", 1.5, 2.0 * 1.5 * (1.5f64 * 1.5).cos()),
+            ("ln(x)/x", 2.0, (1.0 - 2.0f64.ln()) / 4.0),
+            ("2^x", 3.0, 8.0 * 2.0f64.ln()),
+            ("sqrt(1+x^2)", 2.0, 2.0 / 5.0f64.sqrt()),
+            ("tan(x)", 0.5, 1.0 / (0.5f64.cos() * 0.5f64.cos())),
+            ("atan(2x)", 1.0, 2.0 / 5.0),
+            ("abs(x)", -3.0, -1.0),
+            ("max(x, 2, 1)", 3.0, 1.0),
+            ("max(x, 2, 1)", 1.5, 0.0),
+            // ties share the derivative evenly
+            ("max(x, x, x)", 2.0, 1.0),
+            ("min(x, 2, 1)", 1.0, 0.5),
+            ("max(2x, x, x, 4)", 2.0, 1.0),
+            ("mean(x, 3x)", 5.0, 2.0),
+            ("x!", 3.0, 6.0 * (1.5 + 1.0 / 3.0 - 0.5772156649015329)),
+            ("floor(x)", 2.5, 0.0),
+            ("-cos(x)", 1.0, 1.0f64.sin()),
+        ];
+        for (input, x, expected) in cases {
+            let eq = factory.parse(input).unwrap();
+            *eq.variable("x").unwrap() = *x;
+            let derivative = eq.derivative("x").unwrap();
+            assert_near!(*expected, derivative.evaluate().unwrap());
+        }
+
+        // custom bindings may supply a derivative rule
+        bindings::register_bindings(CUBE_FUNCTIONS).unwrap();
+        let mut definitions = syntax::get_definitions(Syntax::Standard).unwrap();
+        definitions
+            .get_mut(&Category::Functions)
+            .unwrap()
+            .rules
+            .push(RuleDefinition {
+                pattern: Some("cube".to_string()),
+                binding: Some("Cube".to_string()),
+                ..Default::default()
+            });
+        let factory = Parser::<f64>::from_definitions(definitions).unwrap();
+        let eq = factory.parse("cube(2x)").unwrap();
+        *eq.variable("x").unwrap() = 1.0;
+        let error = eq
+            .derivative("x")
+            .expect_err("no derivative registered for Cube");
+        assert!(matches!(error.error_type, ErrorType::DifferentiationError));
+        bindings::register_derivatives(CUBE_DERIVATIVES).unwrap();
+        assert_near!(24.0, eq.derivative("x").unwrap().evaluate().unwrap());
+        bindings::register_derivatives::<f64>(&[("NotABinding", cube_derivative)])
+            .expect_err("binding does not exist");
+    }
+
+    #[test]
+    fn test_summation() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        let eq = factory.parse("sum(i, 1, n, i^2)").unwrap();
+        // index variable is bound by the summation, so is not an input
+        assert_eq!(vec!["n".to_string()], eq.variables());
+        *eq.variable("n").unwrap() = 4.0;
+        assert_eq!(30.0, eq.evaluate().unwrap());
+        *eq.variable("n").unwrap() = 0.0;
+        assert_eq!(0.0, eq.evaluate().unwrap());
+
+        assert_eq!(
+            120.0,
+            factory
+                .parse("product(k, 1, 5, k)")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        assert_eq!(
+            1.0,
+            factory
+                .parse("product(k, 1, 0, k)")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        // nested series, with the inner bounds depending on the outer index
+        assert_eq!(
+            14.0,
+            factory
+                .parse("sum(i, 1, 4, sum(j, 1, i, 1)) + sum(i, 1, 2, 2)")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+
+        // a variable with the same name outside the series is a separate input
+        let eq = factory.parse("i * sum(i, 1, 3, i)").unwrap();
+        assert_eq!(1, eq.variables().len());
+        *eq.variable("i").unwrap() = 2.0;
+        assert_eq!(12.0, eq.evaluate().unwrap());
+        assert_eq!(2.0, *eq.variable("i").unwrap());
+
+        factory
+            .parse("sum(1, 1, 3, 1)")
+            .expect_err("index must be a variable");
+        factory
+            .parse("sum(i, 1, 3)")
+            .expect_err("series requires a body");
+        factory
+            .parse("sum(i + 1, 1, 3, i)")
+            .expect_err("index must be a single variable");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+
+        let eq = factory.parse("\\sum_{i=1}^{n} i^2").unwrap();
+        assert_eq!(vec!["n".to_string()], eq.variables());
+        *eq.variable("n").unwrap() = 3.0;
+        assert_eq!(14.0, eq.evaluate().unwrap());
+
+        // the body extends over products but not sums
+        assert_eq!(
+            13.0,
+            factory
+                .parse("\\sum_{i=1}^{3} 2i + 1")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        assert_eq!(
+            15.0,
+            factory
+                .parse("\\sum_{i=1}^{3} (2i + 1)")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        assert_eq!(
+            24.0,
+            factory
+                .parse("\\prod\\limits_{k=1}^{4} k")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        assert_eq!(
+            18.0,
+            factory
+                .parse("\\sum_{i=1}^{3} \\sum_{j=1}^{i} j + 8")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+
+        // an upper bound of one token needs no braces
+        assert_eq!(
+            14.0,
+            factory
+                .parse("\\sum_{i=1}^3 i^2")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        assert_eq!(
+            24.0,
+            factory
+                .parse("\\prod_{i=1}^4 i")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+
+        factory.parse("\\sum i").expect_err("missing bounds");
+        factory.parse("\\sum_{i=1}^{3}").expect_err("missing body");
+        factory
+            .parse("\\sum_{i}^{3} i")
+            .expect_err("missing lower bound");
+
+        // sums and products have no derivative rule
+        let eq = factory.parse("\\sum_{i=1}^{3} i x").unwrap();
+        let error = eq
+            .derivative("x")
+            .expect_err("series cannot be differentiated");
+        assert!(matches!(error.error_type, ErrorType::DifferentiationError));
+    }
+
+    #[test]
+    fn test_integration() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        let eq = factory.parse("x^2 + y").unwrap();
+        *eq.variable("x").unwrap() = 5.0;
+        *eq.variable("y").unwrap() = 1.0;
+        let integral = eq
+            .integrate("x", 0.0, 3.0, &IntegrationOptions::default())
+            .unwrap();
+        assert_near!(12.0, integral.value);
+        assert!(integral.error_estimate < 1e-6);
+        // the variable keeps its value after integration
+        assert_eq!(5.0, *eq.variable("x").unwrap());
+
+        let eq = factory.parse("sin(x)").unwrap();
+        let options = IntegrationOptions {
+            tolerance: 1e-12,
+            max_subdivisions: 200,
+        };
+        let integral = eq
+            .integrate("x", 0.0, std::f64::consts::PI, &options)
+            .unwrap();
+        assert!((2.0 - integral.value).abs() < 1e-12);
+        assert!(integral.error_estimate <= 2.0 * options.tolerance);
+        // a sharp peak needs the range to be subdivided
+        let eq = factory.parse("1 / (0.0001 + x^2)").unwrap();
+        let integral = eq.integrate("x", -1.0, 1.0, &options).unwrap();
+        assert_near!(2.0 * 100.0 * (100.0f64).atan(), integral.value);
+
+        eq.integrate("y", 0.0, 1.0, &options)
+            .expect_err("y is not a variable of the equation");
+        eq.integrate("x", 0.0, f64::INFINITY, &options)
+            .expect_err("bounds must be finite");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+
+        let eq = factory.parse("\\int_{0}^{1} x^2 \\, dx").unwrap();
+        assert_eq!(0, eq.variables().len());
+        assert_near!(1.0 / 3.0, eq.evaluate().unwrap());
+        // bounds of one token need no braces
+        let eq = factory.parse("\\int_0^1 x^2 dx").unwrap();
+        assert_near!(1.0 / 3.0, eq.evaluate().unwrap());
+        let eq = factory.parse("\\int_0^\\pi \\sin x dx").unwrap();
+        assert_near!(2.0, eq.evaluate().unwrap());
+
+        // bounds and body may use other variables, and the body ends at the differential
+        let eq = factory.parse("\\int_{0}^{a} 2 t y \\, dt + 1").unwrap();
+        assert_eq!(2, eq.variables().len());
+        *eq.variable("a").unwrap() = 2.0;
+        *eq.variable("y").unwrap() = 3.0;
+        assert_near!(13.0, eq.evaluate().unwrap());
+
+        assert_near!(
+            0.25,
+            factory
+                .parse("\\int_{0}^{1} \\int_{0}^{1} x y \\, dx \\, \\mathrm{d}y")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+        assert_near!(
+            2.0,
+            factory
+                .parse("\\int_{0}^{\\pi} \\sin(x) dx")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+
+        factory
+            .parse("\\int_{0}^{1} x^2")
+            .expect_err("integral requires a differential");
+        factory
+            .parse("\\int_{0}^{1} dx")
+            .expect_err("integral requires a body");
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        for (equation, expected) in [
+            ("1 < 2", 1.0),
+            ("2 < 1", 0.0),
+            ("2 <= 2", 1.0),
+            ("3 > 2 + 2", 0.0),
+            ("2 >= 1", 1.0),
+            ("1 + 1 == 2", 1.0),
+            ("1 != 1", 0.0),
+            ("1 < 2 && 2 < 1", 0.0),
+            ("1 < 2 or 2 < 1", 1.0),
+            ("1 < 2 || 2 < 1", 1.0),
+            ("0 || 0", 0.0),
+            // inside an absolute value, || closes the bracket and opens another
+            ("|0 || 1|", 0.0),
+            ("0 or 1 and 0", 0.0),
+            ("true and -3", 1.0),
+            ("false or 0", 0.0),
+            ("-(1 < 2)", -1.0),
+        ] {
+            assert_eq!(
+                expected,
+                factory.parse(equation).unwrap().evaluate().unwrap(),
+                "{equation}"
+            );
+        }
+
+        let eq = factory.parse("if(x > 0, x, 0)").unwrap();
+        assert_eq!(vec!["x".to_string()], eq.variables());
+        *eq.variable("x").unwrap() = 2.5;
+        assert_eq!(2.5, eq.evaluate().unwrap());
+        *eq.variable("x").unwrap() = -2.5;
+        assert_eq!(0.0, eq.evaluate().unwrap());
+
+        // pairs of conditions and values are tested in turn, with an optional default at the end
+        let eq = factory.parse("if(x < 0, -1, x == 0, 0, 1) * 10").unwrap();
+        for (x, expected) in [(-4.0, -10.0), (0.0, 0.0), (7.0, 10.0)] {
+            *eq.variable("x").unwrap() = x;
+            assert_eq!(expected, eq.evaluate().unwrap());
+        }
+        assert!(factory
+            .parse("if(x > 0, 1)")
+            .unwrap()
+            .evaluate()
+            .unwrap()
+            .is_nan());
+
+        // only the selected value is evaluated
+        let eq = factory.parse("if(x > 0, x, sum(i, 1, 1/0, i))").unwrap();
+        *eq.variable("x").unwrap() = 1.0;
+        assert_eq!(1.0, eq.evaluate().unwrap());
+        *eq.variable("x").unwrap() = -1.0;
+        assert!(matches!(
+            eq.evaluate().unwrap_err().error_type,
+            ErrorType::EvaluationError
+        ));
+
+        factory
+            .parse("if(1)")
+            .expect_err("conditional requires a condition and a value");
+        factory
+            .parse("if 1")
+            .expect_err("conditional requires brackets");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+
+        let eq = factory
+            .parse("\\begin{cases} x & x > 0 \\\\ -x & \\text{if } x \\le -1 \\\\ 0 & \\text{otherwise} \\end{cases}")
+            .unwrap();
+        for (x, expected) in [(3.0, 3.0), (-2.0, 2.0), (-0.5, 0.0)] {
+            *eq.variable("x").unwrap() = x;
+            assert_eq!(expected, eq.evaluate().unwrap());
+        }
+        for (equation, expected) in [
+            ("1 = 1", 1.0),
+            ("1 \\neq 1", 0.0),
+            ("2 \\geq 3", 0.0),
+            ("2 \\lt 3 \\land 3 \\gt 2", 1.0),
+            ("1 > 2 \\vee 2 \\leq 2", 1.0),
+            ("x \\left(1 \\right)", 0.0),
+        ] {
+            assert_eq!(
+                expected,
+                factory.parse(equation).unwrap().evaluate().unwrap(),
+                "{equation}"
+            );
+        }
+
+        // conditionals have no derivative rule
+        let eq = factory
+            .parse("\\begin{cases} x^2 & x > 0 \\\\ 0 & \\text{otherwise} \\end{cases}")
+            .unwrap();
+        let error = eq
+            .derivative("x")
+            .expect_err("conditionals cannot be differentiated");
+        assert!(matches!(error.error_type, ErrorType::DifferentiationError));
+    }
+
+    #[test]
+    fn test_programs() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        let program = factory
+            .parse_program("r = sqrt(x^2 + y^2); theta = atan(y / x); r * cos(theta)")
+            .unwrap();
+        // assigned variables are not inputs
+        let mut inputs = program.variables().to_vec();
+        inputs.sort();
+        assert_eq!(vec!["x".to_string(), "y".to_string()], inputs);
+        assert_eq!(vec!["r", "theta"], program.assignments());
+        *program.variable("x").unwrap() = 3.0;
+        *program.variable("y").unwrap() = 4.0;
+        assert!((program.evaluate().unwrap() - 3.0).abs() < 1e-12);
+        program
+            .variable("r")
+            .expect_err("assigned variables are not inputs");
+
+        // statements may also be separated by new lines, and variables may be reassigned
+        let program = factory
+            .parse_program("a = 2\n a = a * a;\n\n a == 4;")
+            .unwrap();
+        assert!(program.variables().is_empty());
+        assert_eq!(1.0, program.evaluate().unwrap());
+        // each evaluation starts from the first statement again
+        assert_eq!(1.0, program.evaluate().unwrap());
+
+        // a single expression is a program of one statement
+        let program = factory.parse_program("x + 1").unwrap();
+        *program.variable("x").unwrap() = 1.0;
+        assert_eq!(2.0, program.evaluate().unwrap());
+
+        factory
+            .parse_program("y = x + 1; x = 2; y")
+            .expect_err("variable assigned after being used as an input");
+        factory
+            .parse_program("pi = 3; pi")
+            .expect_err("constants may not be assigned");
+        factory
+            .parse_program("x = ; x")
+            .expect_err("assignment requires a value");
+        factory.parse_program(" ; ").expect_err("program is empty");
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        let program = factory
+            .parse_program(
+                "s := \\frac{a + b + c}{2}; A \\coloneqq \\sqrt{s(s - a)(s - b)(s - c)}; A",
+            )
+            .unwrap();
+        assert_eq!(3, program.variables().len());
+        *program.variable("a").unwrap() = 3.0;
+        *program.variable("b").unwrap() = 4.0;
+        *program.variable("c").unwrap() = 5.0;
+        assert_eq!(6.0, program.evaluate().unwrap());
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        let program = factory
+            .parse_program("f(t) = t^2 + 1; f(x) + f(2x)")
+            .unwrap();
+        // parameters are not inputs, and definitions are not assignments
+        assert_eq!(vec!["x".to_string()], program.variables());
+        assert!(program.assignments().is_empty());
+        *program.variable("x").unwrap() = 3.0;
+        assert_eq!(10.0 + 37.0, program.evaluate().unwrap());
+
+        // functions of several parameters may use variables of the program and earlier functions,
+        // and be called without brackets if they take one argument
+        let program = factory
+            .parse_program(
+                "scale = 2 * k; hyp(a, b) = sqrt(a^2 + b^2); g(x) = scale * hyp(x, 4); g(g 3) - g(3)",
+            )
+            .unwrap();
+        assert_eq!(vec!["k".to_string()], program.variables());
+        *program.variable("k").unwrap() = 0.5;
+        assert!((program.evaluate().unwrap() - (41.0_f64.sqrt() - 5.0)).abs() < 1e-12);
+
+        // a parameter hides a variable of the same name, and a call inside the arguments
+        // of another call to the same function does not affect it
+        let program = factory
+            .parse_program("t = 10; f(t) = t + 1; f(f(t)) + t")
+            .unwrap();
+        assert!(program.variables().is_empty());
+        assert_eq!(22.0, program.evaluate().unwrap());
+
+        factory
+            .parse_program("f(t) = t; f(1, 2)")
+            .expect_err("wrong number of arguments");
+        factory
+            .parse_program("f(t) = t; f(t) = 2t; f(1)")
+            .expect_err("function defined twice");
+        factory
+            .parse_program("f(t) = t; f = 2; f")
+            .expect_err("function name assigned as a variable");
+        factory
+            .parse_program("f(a, a) = a; f(1, 2)")
+            .expect_err("duplicate parameter");
+        factory
+            .parse_program("c() = 1; c()")
+            .expect_err("functions require at least one parameter");
+        factory
+            .parse_program("sin(t) = t; sin(1)")
+            .expect_err("built-in function name");
+        factory
+            .parse_program("f(t) = t +; f(1)")
+            .expect_err("invalid body");
+        factory
+            .parse_program("f(t) = t")
+            .expect_err("program has no statement to evaluate");
+
+        // a function may not refer to itself, nor a parameter share the name of a function
+        for (program, span) in [
+            ("f(t) = f(t) + 1; f(3)", 7..8),
+            ("f(t) = t; g(f) = f + 1; g(2)", 10..16),
+        ] {
+            let error = factory.parse_program(program).unwrap_err();
+            assert_eq!(Some(ErrorCode::InvalidAssignment), error.code, "{program}");
+            assert_eq!(Some(span), error.span, "{program}");
+        }
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        let program = factory
+            .parse_program("f(t) := \\frac{t}{2}; f(x) \\cdot f(4)")
+            .unwrap();
+        *program.variable("x").unwrap() = 3.0;
+        assert_eq!(3.0, program.evaluate().unwrap());
+    }
+
+    #[test]
+    fn test_literal_formats() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        for (equation, expected) in [
+            ("6.02e23", 6.02e23),
+            ("1E-3 * 2", 0.002),
+            ("2.5e+2", 250.0),
+            ("1_000_000 + 1", 1_000_001.0),
+            ("1_000.000_5", 1000.0005),
+            ("0xFF", 255.0),
+            ("0x7f_ff", 32767.0),
+            ("0b1010 - 0o17", -5.0),
+            ("2e-1", 0.2),
+            ("2e - 1", 2.0 * std::f64::consts::E - 1.0),
+            ("2e", 2.0 * std::f64::consts::E),
+            ("2 e^0", 2.0),
+        ] {
+            assert_eq!(
+                expected,
+                factory.parse(equation).unwrap().evaluate().unwrap(),
+                "{equation}"
+            );
+        }
+        // letters after a literal are implicitly multiplied as usual
+        let eq = factory.parse("0xFG").unwrap();
+        *eq.variable("G").unwrap() = 2.0;
+        assert_eq!(30.0, eq.evaluate().unwrap());
+        factory
+            .parse("1__000")
+            .expect_err("separators must be between digits");
+
+        // a syntax using a comma as the decimal point needs a different argument separator
+        let mut definitions = syntax::get_definitions(Syntax::Standard).unwrap();
+        definitions.get_mut(&Category::Separators).unwrap().rules[0].pattern =
+            Some(";".to_string());
+        definitions
+            .get_mut(&Category::Literals)
+            .unwrap()
+            .rules
+            .push(RuleDefinition {
+                pattern: Some("[0-9]+,[0-9]+".to_string()),
+                pattern_is_regex: Some(true),
+                literal_format: Some(LiteralFormat::DecimalComma),
+                ..Default::default()
+            });
+        let factory = Parser::<f64>::from_definitions(definitions).unwrap();
+        assert_eq!(
+            2.5,
+            factory.parse("max(2,5; 2)").unwrap().evaluate().unwrap()
+        );
+
+        let factory = Parser::<f64>::new(Syntax::LaTeX).unwrap();
+        assert_eq!(
+            5.0,
+            factory.parse("2 \\cdot 2{,}5").unwrap().evaluate().unwrap()
+        );
+        assert_eq!(
+            1.204e24,
+            factory
+                .parse("6.02e23 \\cdot 2")
+                .unwrap()
+                .evaluate()
+                .unwrap()
+        );
+
+        // formats may only be given to literals
+        let mut definitions = syntax::get_definitions(Syntax::Standard).unwrap();
+        definitions.get_mut(&Category::Variables).unwrap().rules[0].literal_format =
+            Some(LiteralFormat::Hexadecimal);
+        assert!(Parser::<f64>::from_definitions(definitions).is_err());
+    }
+
+    #[test]
+    fn test_unicode() {
+        let factory = Parser::<f64>::new(Syntax::Standard).unwrap();
+
+        let eq = factory.parse("2πr²").unwrap();
+        *eq.variable("r").unwrap() = 3.0;
+        assert_eq!(2.0 * std::f64::consts::PI * 9.0, eq.evaluate().unwrap());
+
+        let eq = factory.parse("√(x) + a×b − a÷b").unwrap();
+        *eq.variable("x").unwrap() = 16.0;
+        *eq.variable("a").unwrap() = 6.0;
+        *eq.variable("b").unwrap() = 3.0;
+        assert_eq!(4.0 + 18.0 - 2.0, eq.evaluate().unwrap());
+
+        for (equation, expected) in [
+            ("2⁻¹", 0.5),
+            ("−3²", -9.0),
+            ("2³ · 2", 16.0),
+            ("10¹⁰", 1e10),
+            ("1 ≤ 2 ∧ 2 ≠ 3", 1.0),
+            ("¬(1 ≥ 2) ∨ 0", 1.0),
+            ("√4 ⋅ 2", 4.0),
+        ] {
+            assert_eq!(
+                expected,
+                factory.parse(equation).unwrap().evaluate().unwrap(),
+                "{equation}"
+            );
+        }
+
+        // Greek letters are single-letter variables, so they may be implicitly multiplied
+        let eq = factory.parse("αβ + θ").unwrap();
+        assert_eq!(3, eq.variables().len());
+        *eq.variable("α").unwrap() = 2.0;
+        *eq.variable("β").unwrap() = 3.0;
+        *eq.variable("θ").unwrap() = 1.0;
+        assert_eq!(7.0, eq.evaluate().unwrap());
+
+        // full-width characters are read as their ASCII forms
+        let eq = factory.parse("２ｘ＋（１）").unwrap();
+        *eq.variable("x").unwrap() = 4.0;
+        assert_eq!(9.0, eq.evaluate().unwrap());
+        let program = factory.parse_program("ｙ＝３；ｙ＊２").unwrap();
+        assert_eq!(6.0, program.evaluate().unwrap());
+
+        factory
+            .parse("x²⁻")
+            .expect_err("superscript sign must come before its digits");
+    }
 }
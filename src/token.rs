@@ -0,0 +1,38 @@
+use std::ops::Range;
+
+use crate::syntax::Category;
+
+/// A piece of an equation matched by a rule, as found by `Parser::tokenize`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    /// category of the rule that matched the token. The prefixes and separators of a function's argument groups,
+    /// such as the _, = and ^ of \sum_{i=1}^{n}, are Separators, and the differential of an integral, such as the d
+    /// of dx, is Fluff
+    pub category: Category,
+    /// text of the token as written, which is empty for implicit operators
+    pub text: String,
+    /// label of the function binding the token applies, e.g. "Add" for +
+    pub binding: Option<String>,
+    /// byte offsets of the start and end of the token in the equation
+    pub span: Range<usize>,
+    /// character offsets of the start and end of the token in the equation
+    pub char_span: Range<usize>,
+}
+
+impl Token {
+    /// Creates a token starting at the given byte offset, whose character offsets are found later
+    pub(crate) fn new(
+        category: Category,
+        text: &str,
+        binding: Option<String>,
+        start: usize,
+    ) -> Token {
+        Token {
+            category,
+            text: text.to_string(),
+            binding,
+            span: start..start + text.len(),
+            char_span: 0..0,
+        }
+    }
+}